@@ -0,0 +1,442 @@
+use std::collections::HashSet;
+use std::process::ExitCode;
+use std::sync::mpsc;
+
+use clap::{Parser, ValueEnum};
+
+use librusl::{
+    error::ErrorKind,
+    export::{self, ExportFormat},
+    fileinfo::FileInfo,
+    manager::{Manager, SearchResult, WatchEvent},
+    options::{ContentOptions, FTypes, NameOptions, Options, SavedSearch, Sort},
+    rgtools,
+    search::{ContentTerm, Occur, Search},
+};
+
+/// Search file names and contents from the command line
+#[derive(Parser, Debug)]
+#[command(name = "rusl", version, about)]
+struct Args {
+    /// Directories to search in, results of all of them are merged
+    #[arg(default_value = ".", value_name = "DIR")]
+    dir: Vec<String>,
+
+    /// Regex to match file names against
+    #[arg(short, long, default_value = "")]
+    name: String,
+
+    /// Match the name fzf style instead of as a regex, best matches first
+    #[arg(short = 'z', long)]
+    fuzzy: bool,
+
+    /// Regex to match file contents against
+    #[arg(short, long, default_value = "")]
+    content: String,
+
+    /// Files must also contain this regex. Can be repeated
+    #[arg(long, value_name = "REGEX")]
+    and: Vec<String>,
+
+    /// Files must contain at least one of these regexes. Can be repeated
+    #[arg(long, value_name = "REGEX")]
+    or: Vec<String>,
+
+    /// Files must not contain this regex. Can be repeated
+    #[arg(long, value_name = "REGEX")]
+    not: Vec<String>,
+
+    /// Only return files with a size, e.g. >10MB, <=4KiB, 1MB..2MB
+    #[arg(long, value_name = "SIZE", allow_hyphen_values = true)]
+    size: Option<String>,
+
+    /// Only return files modified at a time, e.g. today, last 7 days, >30d (older than 30 days), >2024-01-31, 2024-01-01..2024-01-31
+    #[arg(long, value_name = "WHEN")]
+    modified: Option<String>,
+
+    /// Only return files created at a time, in the same format as --modified
+    #[arg(long, value_name = "WHEN")]
+    created: Option<String>,
+
+    /// Include or exclude files and directories with a glob, e.g. '*.rs' or '!target'. Can be repeated
+    #[arg(short, long, value_name = "GLOB")]
+    glob: Vec<String>,
+
+    /// Only search files of a type, e.g. rust. Can be repeated
+    #[arg(short, long = "type", value_name = "TYPE")]
+    r#type: Vec<String>,
+
+    /// Do not search files of a type. Can be repeated
+    #[arg(short = 'T', long, value_name = "TYPE")]
+    type_not: Vec<String>,
+
+    /// Add a file type, e.g. 'web:*.{html,css}'. Can be repeated
+    #[arg(long, value_name = "TYPE:GLOB")]
+    type_add: Vec<String>,
+
+    /// Show all file types and their globs, then exit
+    #[arg(long)]
+    type_list: bool,
+
+    /// Exclude directories from the walk, e.g. node_modules or target/debug. Can be repeated
+    #[arg(long, value_name = "DIR")]
+    exclude: Vec<String>,
+
+    /// Only return files and directories up to NUM levels deep (1 is the entries directly inside the directory)
+    #[arg(short = 'd', long, value_name = "NUM")]
+    max_depth: Option<usize>,
+
+    /// Only return files and directories at least NUM levels deep
+    #[arg(long, value_name = "NUM")]
+    min_depth: Option<usize>,
+
+    /// Only return files
+    #[arg(long, conflicts_with = "dirs")]
+    files: bool,
+
+    /// Only return directories
+    #[arg(long)]
+    dirs: bool,
+
+    /// Sort results once the search is done [default: none, or relevance with --fuzzy]
+    #[arg(long, value_enum)]
+    sort: Option<SortArg>,
+
+    /// Sort results in descending order, e.g. --sortr modified for the most recently changed first
+    #[arg(long, value_enum, conflicts_with = "sort")]
+    sortr: Option<SortArg>,
+
+    /// Sort results that are equal by --sort or --sortr by this, ascending
+    #[arg(long, value_enum)]
+    then_sort: Option<SortArg>,
+
+    /// Match file names case sensitively
+    #[arg(long)]
+    name_case_sensitive: bool,
+
+    /// Match contents case sensitively
+    #[arg(long)]
+    content_case_sensitive: bool,
+
+    /// Treat the content pattern as a literal string
+    #[arg(short = 'F', long)]
+    fixed_strings: bool,
+
+    /// Allow content matches to span multiple lines
+    #[arg(short = 'U', long)]
+    multiline: bool,
+
+    /// Show NUM lines after each content match
+    #[arg(short = 'A', long, value_name = "NUM")]
+    after_context: Option<usize>,
+
+    /// Show NUM lines before each content match
+    #[arg(short = 'B', long, value_name = "NUM")]
+    before_context: Option<usize>,
+
+    /// Show NUM lines before and after each content match
+    #[arg(short = 'C', long, value_name = "NUM", default_value_t = 0)]
+    context: usize,
+
+    /// Show at most NUM matching lines of each file
+    #[arg(short = 'm', long, value_name = "NUM")]
+    max_count: Option<usize>,
+
+    /// Cut lines longer than NUM chars
+    #[arg(short = 'M', long, value_name = "NUM")]
+    max_columns: Option<usize>,
+
+    /// Stop the search once NUM files are found
+    #[arg(long, value_name = "NUM")]
+    max_results: Option<usize>,
+
+    /// Search inside pdf and office documents
+    #[arg(long)]
+    extended: bool,
+
+    /// Keep an index of the directory, so repeated searches skip files that cannot match
+    #[arg(long)]
+    index: bool,
+
+    /// Include hidden (dot) files and directories
+    #[arg(long)]
+    hidden: bool,
+
+    /// Do not respect .gitignore files
+    #[arg(long)]
+    no_gitignore: bool,
+
+    /// Follow symbolic links
+    #[arg(short = 'L', long)]
+    follow: bool,
+
+    /// Do not cross file system boundaries
+    #[arg(long)]
+    same_filesystem: bool,
+
+    /// Keep running after the search, and print files as they change
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Print the results once the search is done, in a format for other tools
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "watch")]
+    export: Option<ExportArg>,
+
+    /// Run a search saved with --save, or from a GUI, instead of the one given
+    #[arg(long, value_name = "NAME", conflicts_with = "save")]
+    saved: Option<String>,
+
+    /// Save the search and its options under a name, then run it
+    #[arg(long, value_name = "NAME")]
+    save: Option<String>,
+
+    /// Show the saved searches, then exit
+    #[arg(long)]
+    list_saved: bool,
+
+    /// Suppress error messages
+    #[arg(short = 's', long)]
+    no_messages: bool,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SortArg {
+    None,
+    Path,
+    Name,
+    Extension,
+    Relevance,
+    Size,
+    Modified,
+    Matches,
+    Depth,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ExportArg {
+    Json,
+    Csv,
+    RgJson,
+}
+
+impl From<ExportArg> for ExportFormat {
+    fn from(x: ExportArg) -> Self {
+        match x {
+            ExportArg::Json => ExportFormat::Json,
+            ExportArg::Csv => ExportFormat::Csv,
+            ExportArg::RgJson => ExportFormat::RgJson,
+        }
+    }
+}
+
+impl From<SortArg> for Sort {
+    fn from(x: SortArg) -> Self {
+        match x {
+            SortArg::None => Sort::None,
+            SortArg::Path => Sort::Path,
+            SortArg::Name => Sort::Name,
+            SortArg::Extension => Sort::Extension,
+            SortArg::Relevance => Sort::Relevance,
+            SortArg::Size => Sort::Size,
+            SortArg::Modified => Sort::Modified,
+            SortArg::Matches => Sort::Matches,
+            SortArg::Depth => Sort::Depth,
+        }
+    }
+}
+
+impl Args {
+    fn terms(&self) -> Vec<ContentTerm> {
+        let and = self.and.iter().map(|x| ContentTerm::new(x, Occur::Must));
+        let or = self.or.iter().map(|x| ContentTerm::new(x, Occur::Should));
+        let not = self.not.iter().map(|x| ContentTerm::new(x, Occur::MustNot));
+        and.chain(or).chain(not).collect()
+    }
+
+    fn search(&self) -> Search {
+        Search {
+            dirs: self.dir.clone(),
+            name_text: self.name.clone(),
+            contents_text: self.content.clone(),
+            terms: self.terms(),
+        }
+    }
+
+    fn options(&self) -> Options {
+        Options {
+            sort: match self.sort.or(self.sortr) {
+                Some(sort) => sort.into(),
+                None if self.fuzzy => Sort::Relevance,
+                None => Sort::None,
+            },
+            then_sort: self.then_sort.map_or(Sort::None, Sort::from),
+            sort_descending: self.sortr.is_some(),
+            name: NameOptions {
+                case_sensitive: self.name_case_sensitive,
+                file_types: match (self.files, self.dirs) {
+                    (true, _) => FTypes::Files,
+                    (_, true) => FTypes::Directories,
+                    _ => FTypes::All,
+                },
+                same_filesystem: self.same_filesystem,
+                follow_links: self.follow,
+                ignore_dot: !self.hidden,
+                use_gitignore: !self.no_gitignore,
+                size: self.size.clone().unwrap_or_default(),
+                modified: self.modified.clone().unwrap_or_default(),
+                created: self.created.clone().unwrap_or_default(),
+                globs: self.glob.clone(),
+                types: self.r#type.iter().cloned().chain(self.type_not.iter().map(|t| format!("!{t}"))).collect(),
+                type_defs: self.type_add.clone(),
+                max_depth: self.max_depth,
+                min_depth: self.min_depth,
+                exclude_dirs: self.exclude.clone(),
+                fuzzy: self.fuzzy,
+            },
+            content: ContentOptions {
+                case_sensitive: self.content_case_sensitive,
+                extended: self.extended,
+                nonregex: self.fixed_strings,
+                multiline: self.multiline,
+                before_context: self.before_context.unwrap_or(self.context),
+                after_context: self.after_context.unwrap_or(self.context),
+                use_index: self.index,
+                max_count: self.max_count,
+                max_line_len: self.max_columns,
+                ..Default::default()
+            },
+            max_results: self.max_results,
+            ..Default::default()
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    if args.type_list {
+        for (name, globs) in rgtools::type_definitions(&args.options().name) {
+            println!("{name}: {}", globs.join(", "));
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let (s, r) = mpsc::channel();
+    let mut man = Manager::new(s);
+    if args.list_saved {
+        for saved in man.get_options().saved_searches {
+            let search = &saved.search;
+            println!(
+                "{}: name '{}' content '{}' in {}",
+                saved.name,
+                search.name_text,
+                search.contents_text,
+                search.dirs.join(", ")
+            );
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let search = match &args.saved {
+        Some(name) => match man.load_saved_search(name) {
+            Some(search) => search,
+            None => {
+                eprintln!("rusl: no saved search {name}, see --list-saved");
+                return ExitCode::from(2);
+            }
+        },
+        None => args.search(),
+    };
+    if !search.has_contents() && search.name_text.is_empty() {
+        eprintln!("rusl: nothing to search for, use --name and/or --content");
+        return ExitCode::from(2);
+    }
+    if let Some(dir) = search.dirs.iter().find(|x| !man.dir_is_valid(x)) {
+        eprintln!("rusl: invalid directory {dir}");
+        return ExitCode::from(2);
+    }
+    //a saved search runs with its own options, loaded above
+    if args.saved.is_none() {
+        let mut ops = args.options();
+        //user defined extractors are kept from the config
+        ops.content.extractors = man.get_options().content.extractors;
+        if let Err(err) = Manager::validate(&search, &ops) {
+            eprintln!("rusl: {err}");
+            return ExitCode::from(2);
+        }
+        //saved before the options are set, which are only for this run
+        if let Some(name) = &args.save {
+            man.save_search(SavedSearch::new(name, &search, &ops));
+        }
+        man.set_options(ops);
+    }
+    //when sorting or exporting, interim results arrive unsorted, so we wait for the final results
+    let ops = man.get_options();
+    let streaming = matches!((ops.sort, ops.then_sort), (Sort::None, Sort::None)) && args.export.is_none();
+    if let Err(err) = man.search(&search) {
+        eprintln!("rusl: {err}");
+        return ExitCode::from(2);
+    }
+
+    let mut printed = HashSet::new();
+    loop {
+        let Ok(mess) = r.recv() else {
+            eprintln!("rusl: search ended unexpectedly");
+            return ExitCode::from(2);
+        };
+        match mess {
+            SearchResult::InterimResult(fi) => {
+                if streaming {
+                    print_fileinfo(&fi);
+                    printed.insert(fi.path);
+                }
+            }
+            SearchResult::FinalResults(fr) => {
+                if fr.truncated && !args.no_messages {
+                    eprintln!("rusl: stopped after {} results", fr.data.len());
+                }
+                if let Some(format) = args.export {
+                    print!("{}", export::export(&fr, format.into()));
+                    return if fr.data.is_empty() { ExitCode::from(1) } else { ExitCode::SUCCESS };
+                }
+                for fi in fr.data.iter().filter(|x| !printed.contains(&x.path)) {
+                    print_fileinfo(fi);
+                }
+                if !args.watch {
+                    return if fr.data.is_empty() { ExitCode::from(1) } else { ExitCode::SUCCESS };
+                }
+                if let Err(err) = man.watch() {
+                    eprintln!("rusl: {err}");
+                    return ExitCode::from(2);
+                }
+            }
+            SearchResult::SearchErrors(errs) => {
+                //like rg, binary files are skipped quietly
+                if !args.no_messages {
+                    for err in errs.iter().filter(|x| x.kind() != ErrorKind::Binary) {
+                        eprintln!("rusl: {err}");
+                    }
+                }
+            }
+            SearchResult::SearchCount(_) => {}
+            SearchResult::Watch(event) => match event {
+                WatchEvent::Added(fi) | WatchEvent::Updated(fi) => print_fileinfo(&fi),
+                WatchEvent::Removed(path) => println!("{path} (removed)"),
+            },
+        }
+    }
+}
+
+fn print_fileinfo(fi: &FileInfo) {
+    if fi.matches.is_empty() {
+        println!("{}", fi.path);
+    }
+    for mat in fi.unique_lines() {
+        //like grep, context lines are separated with - instead of :
+        let sep = if mat.is_context { '-' } else { ':' };
+        //multiline matches are printed with a line number for each line
+        for (i, line) in mat.content.lines().enumerate() {
+            println!("{}{sep}{}{sep}{}", fi.path, mat.line + i, line.trim_end());
+        }
+    }
+}
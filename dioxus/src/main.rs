@@ -1,14 +1,22 @@
 //hide windows console
 #![windows_subsystem = "windows"]
 
-use std::{sync::mpsc, time::Duration};
+use std::{collections::BTreeMap, sync::mpsc, time::Duration};
 
 //use dioxus_desktop::{tao::window::Icon, Config, WindowBuilder};
 use dioxus::{
     desktop::{WindowBuilder, tao::window::Icon},
     prelude::*,
 };
-use librusl::{fileinfo::FileInfo, manager::Manager, search::Search};
+use librusl::{
+    error::{ErrorKind, PatternError, PatternField, SearchError},
+    export,
+    fileinfo::FileInfo,
+    manager::{FinalResults, Manager},
+    open::OpenTarget,
+    options::SavedSearch,
+    search::{ContentTerm, Search},
+};
 pub fn main() {
     println!("run with `WEBKIT_DISABLE_DMABUF_RENDERER=1` if you have problems on linux wayland");
 
@@ -41,7 +49,16 @@ fn app() -> Element {
     let mut text_contents = use_signal(|| "".to_string());
     let mut text_dir = use_signal(|| ".".to_string());
     let mut message = use_signal(|| "".to_string());
+    let mut text_saved = use_signal(|| "".to_string());
+    //there are no boxes for terms, they come from a loaded saved search
+    let mut terms = use_signal(|| Vec::<ContentTerm>::new());
     let data = use_signal(|| Vec::<FileInfo>::new());
+    //all the results of the last search, for saving
+    let mut final_results = use_signal(|| Option::<FinalResults>::None);
+    let mut errors = use_signal(|| Vec::<SearchError>::new());
+    let mut pattern_error = use_signal(|| Option::<PatternError>::None);
+    //keep the results live after a search
+    let mut watch = use_signal(|| false);
     let (s, r) = mpsc::channel();
     let mut man = use_signal(|| Manager::new(s));
     let count = use_signal(|| 0);
@@ -54,32 +71,48 @@ fn app() -> Element {
         let mut data = data.clone();
         let mut message = message.clone();
         let mut count = count.clone();
+        let mut errors = errors.clone();
+        let mut final_results = final_results.clone();
+        let mut man = man.clone();
         async move {
             loop {
                 match r.read().try_recv() {
                     Ok(files) => {
+                        let shown = man.read().get_options().shown_results();
                         match files {
                             librusl::manager::SearchResult::FinalResults(fe) => {
                                 eprintln!("Found {}", fe.data.len());
+                                final_results.set(Some(fe.clone()));
                                 let current = data.clone();
                                 let mut mutable = current.clone();
                                 mutable.write().clear();
                                 let found_count = fe.data.len();
-                                if found_count < 1000 {
+                                if found_count <= shown {
                                     mutable.extend(fe.data);
                                 } else {
-                                    mutable.extend(fe.data.into_iter().take(1000));
+                                    mutable.extend(fe.data.into_iter().take(shown));
                                     mutable.push(FileInfo {
-                                        path: format!("...and {} more", found_count - 1000),
+                                        path: format!("...and {} more", found_count - shown),
+                                        root: "".to_string(),
                                         matches: vec![],
                                         ext: "".to_string(),
                                         name: "".to_string(),
                                         is_folder: false,
                                         plugin: None,
                                         ranges: vec![],
+                                        score: None,
+                                        size: None,
+                                        modified: None,
                                     });
                                 };
-                                message.set(format!("Found {} in {:.2}s", found_count, fe.duration.as_secs_f32()));
+                                let truncated = if fe.truncated { " (max results reached)" } else { "" };
+                                message.set(format!("Found {} in {:.2}s{truncated}", found_count, fe.duration.as_secs_f32()));
+                                if watch()
+                                    && !man.read().is_watching()
+                                    && let Err(err) = man.with_mut(|x| x.watch())
+                                {
+                                    errors.write().push(SearchError::other(None, err));
+                                }
                             }
                             librusl::manager::SearchResult::InterimResult(ir) => {
                                 let c = *count.read();
@@ -90,22 +123,36 @@ fn app() -> Element {
                                 //eprintln!("{mes}");
                                 message.set(mes);
 
-                                if mutable.len() < 1000 {
+                                if mutable.len() < shown {
                                     mutable.push(ir);
-                                } else if mutable.len() == 1000 {
+                                } else if mutable.len() == shown {
                                     mutable.push(FileInfo {
                                         path: format!("...and others"),
+                                        root: "".to_string(),
                                         matches: vec![],
                                         ext: "".to_string(),
                                         name: "".to_string(),
                                         is_folder: false,
                                         plugin: None,
                                         ranges: vec![],
+                                        score: None,
+                                        size: None,
+                                        modified: None,
                                     });
                                 }
                             }
-                            librusl::manager::SearchResult::SearchErrors(_) => { /*todo show errors*/ }
+                            //like rg, binary files are skipped quietly
+                            librusl::manager::SearchResult::SearchErrors(errs) => {
+                                errors.write().extend(errs.into_iter().filter(|x| x.kind() != ErrorKind::Binary))
+                            }
                             librusl::manager::SearchResult::SearchCount(_) => {}
+                            librusl::manager::SearchResult::Watch(event) => {
+                                if let Some(results) = &mut *final_results.write() {
+                                    results.apply(&event);
+                                    data.set(results.data.iter().take(shown).cloned().collect());
+                                }
+                                message.set(format!("{event} (watching)"));
+                            }
                         }
                     }
 
@@ -117,6 +164,14 @@ fn app() -> Element {
         }
     });
 
+    let groups = error_groups(&errors.read());
+    //terms have no box, so are shown with the contents
+    let (name_error, content_error) = match &*pattern_error.read() {
+        Some(err) if err.field == PatternField::Name => (err.to_string(), String::new()),
+        Some(err) => (String::new(), err.to_string()),
+        None => (String::new(), String::new()),
+    };
+
     rsx!(
 
 
@@ -136,6 +191,9 @@ fn app() -> Element {
                     }
                     label { style: "color:white", "File name" }
                 }
+                if !name_error.is_empty() {
+                    div { style: "color:#c86464", "{name_error}" }
+                }
                 div { class: "mui-textfield",
                     input {
                         style: "color:lightgray;",
@@ -148,6 +206,9 @@ fn app() -> Element {
                     }
                     label { style: "color:white", "Contents" }
                 }
+                if !content_error.is_empty() {
+                    div { style: "color:#c86464", "{content_error}" }
+                }
                 div { class: "mui-textfield",
                     input {
                         style: "color:lightgray;",
@@ -157,9 +218,17 @@ fn app() -> Element {
                             text_dir.set(newval);
                         }
                     }
-                    label { style: "color:white", "Directory" }
+                    label { style: "color:white", "Directories" }
                 }
                 div { "{message}" }
+                for (kind, errs) in groups {
+                    details {
+                        summary { "{kind} ({errs.len()})" }
+                        for err in errs {
+                            div { style: "color:lightgray", "{err}" }
+                        }
+                    }
+                }
                 div {
                     button {
                         class: "mui-btn mui-btn--primary mui-btn--raised",
@@ -168,20 +237,97 @@ fn app() -> Element {
                                 message.set("Nothing to search for".to_string());
                             } else {
                                 message.set("Searching".to_string());
-                                man.with_mut(|x| {
-                                    x.search(
-                                        &Search {
-                                            name_text: text_name.to_string(),
-                                            contents_text: text_contents.to_string(),
-                                            dir: text_dir.to_string(),
-                                            ..Default::default()
-                                        },
-                                    )
-                                });
+                                errors.write().clear();
+                                final_results.set(None);
+                                if !watch() {
+                                    man.with_mut(|x| x.unwatch());
+                                }
+                                let result = man
+                                    .with_mut(|x| {
+                                        x.search(
+                                            &Search {
+                                                name_text: text_name.to_string(),
+                                                contents_text: text_contents.to_string(),
+                                                dirs: Search::split_dirs(&text_dir.read()),
+                                                terms: terms.read().clone(),
+                                            },
+                                        )
+                                    });
+                                if result.is_err() {
+                                    message.set("Invalid regex".to_string());
+                                }
+                                pattern_error.set(result.err());
                             }
                         },
                         "Find"
                     }
+                    label {
+                        input {
+                            r#type: "checkbox",
+                            checked: watch(),
+                            onchange: move |evt| watch.set(evt.checked()),
+                        }
+                        " Watch for changes after search"
+                    }
+                    if final_results.read().is_some() {
+                        button {
+                            class: "mui-btn",
+                            onclick: move |_| save_results(final_results, message),
+                            "Save results"
+                        }
+                    }
+                }
+                div { class: "mui-textfield",
+                    input {
+                        style: "color:lightgray;",
+                        value: "{text_saved}",
+                        placeholder: "Name to save the search as",
+                        oninput: move |evt| {
+                            let newval = evt.value().clone();
+                            text_saved.set(newval);
+                        }
+                    }
+                    label { style: "color:white", "Saved searches" }
+                }
+                div {
+                    button {
+                        class: "mui-btn",
+                        onclick: move |_| {
+                            let name = text_saved.read().trim().to_string();
+                            if name.is_empty() {
+                                message.set("Name the search to save it".to_string());
+                                return;
+                            }
+                            let search = Search {
+                                name_text: text_name.to_string(),
+                                contents_text: text_contents.to_string(),
+                                dirs: Search::split_dirs(&text_dir.read()),
+                                terms: terms.read().clone(),
+                            };
+                            man.with_mut(|x| {
+                                let saved = SavedSearch::new(&name, &search, &x.get_options());
+                                x.save_search(saved)
+                            });
+                            message.set(format!("Saved search {name}"));
+                        },
+                        "Save search"
+                    }
+                    for saved in man.read().get_options().saved_searches {
+                        button {
+                            class: "mui-btn mui-btn--flat",
+                            onclick: move |_| {
+                                if let Some(search) = man.with_mut(|x| x.load_saved_search(&saved.name)) {
+                                    text_name.set(search.name_text);
+                                    text_contents.set(search.contents_text);
+                                    text_dir.set(Search::join_dirs(&search.dirs));
+                                    terms.set(search.terms);
+                                    text_saved.set(saved.name.clone());
+                                    message.set(format!("Loaded search {}", saved.name));
+                                }
+                            },
+                            "{saved.name}"
+                        }
+                    }
                 }
             }
 
@@ -194,12 +340,28 @@ fn app() -> Element {
                     div{
                         label{
                             style:if text_contents.read().is_empty(){""}else{ "color:blue;font-weight:bold"},
+                            title: "Double click to open, right click to open the folder",
+                            ondoubleclick: {
+                                let target = OpenTarget::File(x.path.clone(), None);
+                                move |_| open(&target, man, message)
+                            },
+                            oncontextmenu: {
+                                let target = OpenTarget::Folder(x.path.clone());
+                                move |evt: MouseEvent| {
+                                    evt.prevent_default();
+                                    open(&target, man, message)
+                                }
+                            },
                             "{x.path}"
                         },
                     }
-                    for mat in &x.matches{
+                    for mat in x.unique_lines(){
 
                             div{
+                            ondoubleclick: {
+                                let target = OpenTarget::File(x.path.clone(), Some(mat.line));
+                                move |_| open(&target, man, message)
+                            },
                             label{
                                 style:"color:darkgreen;font-weight:bold",
                                 "{mat.line}: "
@@ -219,6 +381,41 @@ fn app() -> Element {
     )
 }
 
+///errors by kind, to show in collapsed groups
+fn error_groups(errors: &[SearchError]) -> BTreeMap<ErrorKind, Vec<String>> {
+    let mut groups: BTreeMap<ErrorKind, Vec<String>> = BTreeMap::new();
+    for err in errors {
+        groups.entry(err.kind()).or_default().push(err.to_string());
+    }
+    groups
+}
+
+///saves to a file picked by the user, in the format of its extension
+fn save_results(results: Signal<Option<FinalResults>>, mut message: Signal<String>) {
+    let file = rfd::FileDialog::new()
+        .add_filter("JSON", &["json"])
+        .add_filter("CSV", &["csv"])
+        .add_filter("ripgrep JSON Lines", &["jsonl"])
+        .add_filter("Paths", &["txt"])
+        .set_file_name("results.json")
+        .save_file();
+    if let (Some(file), Some(results)) = (file, &*results.read()) {
+        match export::save(results, &file) {
+            Ok(()) => message.set(format!("Saved {}", file.display())),
+            Err(err) => message.set(err),
+        }
+    }
+}
+
+///with the editor of the options, see `OpenTarget::open`
+fn open(target: &OpenTarget, man: Signal<Manager>, mut message: Signal<String>) {
+    let editor = man.read().get_options().editor;
+    match target.open(&editor) {
+        Ok(()) => message.set(format!("Opened {}", target.path())),
+        Err(err) => message.set(err),
+    }
+}
+
 fn limit_len(s: &str, max: usize) -> &str {
     match s.char_indices().nth(max) {
         None => s,
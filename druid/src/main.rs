@@ -2,6 +2,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::{
+    collections::{BTreeMap, HashSet},
     sync::{mpsc, Arc},
     thread::spawn,
     time::{Duration, Instant},
@@ -12,7 +13,7 @@ use druid::HasRawWindowHandle;
 
 use druid::{
     im::Vector,
-    text::{Attribute, RichText, RichTextBuilder},
+    text::{Attribute, ParseFormatter, RichText, RichTextBuilder},
     widget::{Button, Checkbox, Controller, Either, Flex, Label, List, RadioGroup, RawLabel, Scroll, SizedBox, TextBox},
     AppDelegate, AppLauncher, Application, Code, Color, Command, Data, Env, Event, EventCtx, FontFamily, FontWeight, Handled, Lens, Selector, Target,
     Widget, WidgetExt, WindowDesc,
@@ -21,11 +22,14 @@ use druid::{
 use regex::{Regex, RegexBuilder};
 
 use librusl::{
-    extended::ExtendedTrait,
-    fileinfo::FileInfo,
+    error::{ErrorKind, PatternError, PatternField, SearchError},
+    export,
+    fileinfo::{FileInfo, Match},
     manager::{FinalResults, Manager, SearchResult},
-    options::FTypes,
-    search::Search,
+    open::OpenTarget,
+    options::{ContentOptions, FTypes, Options, SavedSearch, Sort},
+    replace::{self, FileReplacement, ReplaceApplied, ReplacePreview},
+    search::{ContentTerm, Occur, Search, DIR_SEPARATOR},
 };
 
 pub const SEARCH: Selector = Selector::new("search");
@@ -34,16 +38,28 @@ pub const RESULTS: Selector<SearchResult> = Selector::new("results");
 pub const UPDATEMESSAGE: Selector<String> = Selector::new("message");
 pub const EXPORT: Selector = Selector::new("export");
 pub const EXPORTSINGLE: Selector<String> = Selector::new("exportsingle");
+pub const RESULTCLICKED: Selector<OpenTarget> = Selector::new("resultclicked");
+pub const SAVE: Selector = Selector::new("save");
+pub const REPLACEPREVIEW: Selector = Selector::new("replacepreview");
+pub const REPLACEAPPLY: Selector = Selector::new("replaceapply");
+pub const REPLACEUNDO: Selector = Selector::new("replaceundo");
+pub const SAVESEARCH: Selector = Selector::new("savesearch");
+pub const LOADSEARCH: Selector<String> = Selector::new("loadsearch");
+pub const REMOVESEARCH: Selector<String> = Selector::new("removesearch");
+pub const TOGGLEERRORS: Selector<ErrorKind> = Selector::new("toggleerrors");
 
-const MAX_NAMES: usize = 1000;
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
 #[derive(Data, Clone, Lens)]
 struct AppState {
     text_name: String,
     text_contents: String,
+    text_replace: String,
     dir: String,
     message: RichText,
     count: String,
     error_message: String,
+    name_error: String, //invalid regex of the name box
+    content_error: String,
     showing_errors: bool,
     visible: Vector<RichText>,
     visible_errors: Vector<RichText>,
@@ -51,25 +67,49 @@ struct AppState {
     searching: bool,
     data: Vector<String>,
     done: bool,
+    can_apply: bool,
+    can_undo: bool,
 
     start: Instant,
     show_settings: bool,
     //settings
     name_case_sensitive: bool,
+    name_fuzzy: bool,
     name_same_filesystem: bool,
     name_follow_links: bool,
     name_ignore_dot: bool,
     name_search_file_type: SearchFileType,
     name_use_gitignore: bool,
+    name_size: String,
+    name_modified: String,
+    name_created: String,
+    name_globs: String,
+    name_types: String,
+    name_min_depth: String,
+    name_max_depth: String,
+    name_exclude_dirs: String,
     content_case_sensitive: bool,
     content_extended: bool,
     content_nonregex: bool,
+    content_multiline: bool,
+    content_use_index: bool,
+    content_before_context: usize,
+    content_after_context: usize,
+    content_max_count: String,
+    content_max_line_len: String,
+    max_results: String,
+    shown_results: usize, //see `Options::shown_results`, of the last search
+    replace_backup: bool,
+    watch: bool,
+    editor: String,
+    saved_name: String,
+    saved_names: Vector<String>,
+    #[data(ignore)]
+    terms: Arc<Vec<ContentTerm>>,
     //regex
     #[data(ignore)]
     re_name: Result<Regex, regex::Error>,
     #[data(ignore)]
-    re_content: Result<Regex, regex::Error>,
-    #[data(ignore)]
     re_line: Result<Regex, regex::Error>,
 
     //update
@@ -79,10 +119,22 @@ struct AppState {
     interim_count: usize,
     #[data(ignore)]
     searched_count: usize,
+    #[data(ignore)]
+    last_click: Option<(Instant, OpenTarget)>,
+    #[data(ignore)]
+    search_errors: Vec<SearchError>,
+    #[data(ignore)]
+    hidden_errors: HashSet<ErrorKind>, //kinds of errors collapsed in the error list
 
     //results
     #[data(ignore)]
     raw_data: Arc<Option<FinalResults>>,
+    #[data(ignore)]
+    searched: Arc<Option<(Search, ContentOptions)>>, //what the results were searched with, for the replacement
+    #[data(ignore)]
+    replace_preview: Arc<Option<ReplacePreview>>,
+    #[data(ignore)]
+    replace_applied: Arc<Option<ReplaceApplied>>,
 }
 
 pub fn main() {
@@ -92,15 +144,17 @@ pub fn main() {
 
     //regex constants
     let rename = Regex::new("");
-    let recontent = Regex::new("");
     let reline = Regex::new(r"(^|\n)(\d+:)");
 
     let data = AppState {
         text_name: String::new(),
         text_contents: String::new(),
+        text_replace: String::new(),
         dir: ops.last_dir.to_string(),
         message: RichText::new("Ready to search".into()),
         error_message: String::new(),
+        name_error: String::new(),
+        content_error: String::new(),
         showing_errors: false,
         data: Vector::new(),
         visible: Vector::new(),
@@ -111,27 +165,57 @@ pub fn main() {
         interim_count: 0,
         searched_count: 0,
         done: true,
+        can_apply: false,
+        can_undo: false,
 
         find_name: String::from("Find"),
         count: String::new(),
         //settings
         name_case_sensitive: ops.name.case_sensitive,
+        name_fuzzy: ops.name.fuzzy,
         name_same_filesystem: ops.name.same_filesystem,
         name_follow_links: ops.name.follow_links,
         name_ignore_dot: ops.name.ignore_dot,
         name_use_gitignore: ops.name.use_gitignore,
+        name_size: ops.name.size.clone(),
+        name_modified: ops.name.modified.clone(),
+        name_created: ops.name.created.clone(),
+        name_globs: ops.name.globs.join(" "),
+        name_types: ops.name.types.join(" "),
+        name_min_depth: ops.name.min_depth.map(|d| d.to_string()).unwrap_or_default(),
+        name_max_depth: ops.name.max_depth.map(|d| d.to_string()).unwrap_or_default(),
+        name_exclude_dirs: ops.name.exclude_dirs.join(" "),
         name_search_file_type: SearchFileType::All,
         content_case_sensitive: ops.content.case_sensitive,
         content_extended: ops.content.extended,
         content_nonregex: ops.content.nonregex,
+        content_multiline: ops.content.multiline,
+        content_use_index: ops.content.use_index,
+        content_before_context: ops.content.before_context,
+        content_after_context: ops.content.after_context,
+        content_max_count: ops.content.max_count.map(|d| d.to_string()).unwrap_or_default(),
+        content_max_line_len: ops.content.max_line_len.map(|d| d.to_string()).unwrap_or_default(),
+        max_results: ops.max_results.map(|d| d.to_string()).unwrap_or_default(),
+        shown_results: ops.shown_results(),
+        replace_backup: true,
+        watch: false,
+        editor: ops.editor.clone(),
+        saved_name: String::new(),
+        saved_names: saved_names(&ops),
+        terms: Arc::new(vec![]),
         //regex
         re_name: rename,
-        re_content: recontent,
         re_line: reline,
         //update
         last_update: Instant::now(),
+        last_click: None,
+        search_errors: vec![],
+        hidden_errors: HashSet::new(),
 
         raw_data: Arc::new(None),
+        searched: Arc::new(None),
+        replace_preview: Arc::new(None),
+        replace_applied: Arc::new(None),
     };
     let delegate = Delegate { manager: man };
 
@@ -162,7 +246,25 @@ fn ui_builder() -> impl Widget<AppState> {
         .controller(TextBoxController {})
         .lens(AppState::text_contents)
         .expand_width();
+    let treplace = TextBox::new()
+        .with_placeholder("Replace content matches with, e.g. new_$1")
+        .lens(AppState::text_replace)
+        .expand_width();
+    let butpreview = Button::new("Preview")
+        .on_click(|ctx, _data, _env| ctx.submit_command(REPLACEPREVIEW))
+        .fix_size(80., 30.);
+    let butapply = Either::new(
+        |data: &AppState, _env| data.can_apply,
+        Button::new("Apply").on_click(|ctx, _data, _env| ctx.submit_command(REPLACEAPPLY)).fix_size(80., 30.),
+        SizedBox::empty().fix_size(80., 30.),
+    );
+    let butundo = Either::new(
+        |data: &AppState, _env| data.can_undo,
+        Button::new("Undo").on_click(|ctx, _data, _env| ctx.submit_command(REPLACEUNDO)).fix_size(80., 30.),
+        SizedBox::empty().fix_size(80., 30.),
+    );
     let tdir = TextBox::new()
+        .with_placeholder(format!("Directories, separated by {DIR_SEPARATOR}"))
         .controller(TextBoxController {})
         .fix_width(300.)
         .lens(AppState::dir)
@@ -182,6 +284,9 @@ fn ui_builder() -> impl Widget<AppState> {
     let butclip = Button::new("Clipboard")
         .on_click(|ctx, _data, _env| ctx.submit_command(EXPORT))
         .fix_size(85., 40.);
+    let butsave = Button::new("Save")
+        .on_click(|ctx, _data, _env| ctx.submit_command(SAVE))
+        .fix_size(60., 40.);
     let lmessage = RawLabel::new().lens(AppState::message).padding(5.0).center().expand_width();
     let lcount = RawLabel::new().lens(AppState::count).padding(5.0);
     let berrors = Either::new(
@@ -195,10 +300,21 @@ fn ui_builder() -> impl Widget<AppState> {
     );
 
     // RawLabel::new().lens(AppState::error_message).padding(5.0).fix_width(80.).align_right();
+    //several folders can be picked at once, and more added to the ones already there
     let butfolder: SizedBox<AppState> = Button::new("📁")
         .on_click(|_ctx, data: &mut AppState, _env| {
-            if let Some(folder) = rfd::FileDialog::new().pick_folder() {
-                data.dir = folder.to_string_lossy().to_string();
+            if let Some(folders) = rfd::FileDialog::new().pick_folders() {
+                let dirs: Vec<String> = folders.iter().map(|x| x.to_string_lossy().to_string()).collect();
+                data.dir = Search::join_dirs(&dirs);
+            }
+        })
+        .fix_size(40., 30.);
+    let butaddfolder: SizedBox<AppState> = Button::new("➕")
+        .on_click(|_ctx, data: &mut AppState, _env| {
+            if let Some(folders) = rfd::FileDialog::new().pick_folders() {
+                let mut dirs = Search::split_dirs(&data.dir);
+                dirs.extend(folders.iter().map(|x| x.to_string_lossy().to_string()));
+                data.dir = Search::join_dirs(&dirs);
             }
         })
         .fix_size(40., 30.);
@@ -218,16 +334,28 @@ fn ui_builder() -> impl Widget<AppState> {
                 .with_flex_child(tname, 1.0)
                 .with_spacer(5.),
         )
+        .with_child(pattern_error(|data| &data.name_error))
         .with_child(
             Flex::row()
                 .with_child(Label::new("Contents").padding(5.0).fix_width(100.))
                 .with_flex_child(tcontents, 1.0)
                 .with_spacer(5.),
         )
+        .with_child(pattern_error(|data| &data.content_error))
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Replace").padding(5.0).fix_width(100.))
+                .with_flex_child(treplace, 1.0)
+                .with_child(butpreview)
+                .with_child(butapply)
+                .with_child(butundo)
+                .with_spacer(5.),
+        )
         .with_child(
             Flex::row()
-                .with_child(Label::new("Directory").padding(5.0).fix_width(100.))
+                .with_child(Label::new("Directories").padding(5.0).fix_width(100.))
                 .with_child(butfolder)
+                .with_child(butaddfolder)
                 .with_flex_child(tdir, 1.0)
                 .with_spacer(5.),
         )
@@ -238,6 +366,7 @@ fn ui_builder() -> impl Widget<AppState> {
                 .with_flex_spacer(1.)
                 .with_child(butset)
                 .with_child(butclip)
+                .with_child(butsave)
                 .with_spacer(5.),
         )
         .with_child(settings_panel())
@@ -249,12 +378,26 @@ fn ui_builder() -> impl Widget<AppState> {
         )
         .with_flex_child(Either::new(|st, _env| st.showing_errors, error_list, list), 1.0)
 }
+///the invalid regex of a text box, under it
+fn pattern_error(error: fn(&AppState) -> &String) -> impl Widget<AppState> {
+    Either::new(
+        move |data: &AppState, _env| error(data).is_empty(),
+        SizedBox::empty(),
+        Flex::row().with_spacer(100.).with_child(
+            Label::dynamic(move |data: &AppState, _env| error(data).clone())
+                .with_text_color(Color::rgb8(200, 100, 100))
+                .padding(2.0),
+        ),
+    )
+}
+
 fn settings_panel() -> impl Widget<AppState> {
     Either::new(
         |data: &AppState, _env| data.show_settings,
         Flex::column()
             .with_child(Label::new("Name Settings").align_left().padding(10.))
             .with_child(Checkbox::new("Case sensitive").lens(AppState::name_case_sensitive).align_left())
+            .with_child(Checkbox::new("Fuzzy match (fzf style), best matches first").lens(AppState::name_fuzzy).align_left())
             .with_child(Checkbox::new("Same filesystem").lens(AppState::name_same_filesystem).align_left())
             .with_child(Checkbox::new("Ignore hidden (dot)").lens(AppState::name_ignore_dot).align_left())
             .with_child(Checkbox::new("Use gitignore").lens(AppState::name_use_gitignore).align_left())
@@ -268,10 +411,92 @@ fn settings_panel() -> impl Widget<AppState> {
                 .lens(AppState::name_search_file_type)
                 .align_left(),
             ) // Radio::new("All", true).lens(AppState::type_all)))
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Size"))
+                    .with_child(filter_box(">10MB", 100.).lens(AppState::name_size))
+                    .with_child(Label::new("Modified"))
+                    .with_child(filter_box("last 7 days", 100.).lens(AppState::name_modified))
+                    .with_child(Label::new("Created"))
+                    .with_child(filter_box("<2024-01-31", 100.).lens(AppState::name_created))
+                    .align_left(),
+            )
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Globs"))
+                    .with_child(filter_box("*.rs !target", 200.).lens(AppState::name_globs))
+                    .with_child(Label::new("Types"))
+                    .with_child(filter_box("rust !js", 200.).lens(AppState::name_types))
+                    .align_left(),
+            )
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Depth from"))
+                    .with_child(filter_box("", 50.).lens(AppState::name_min_depth))
+                    .with_child(Label::new("to"))
+                    .with_child(filter_box("", 50.).lens(AppState::name_max_depth))
+                    .with_child(Label::new("Exclude folders"))
+                    .with_child(filter_box("node_modules .cache", 200.).lens(AppState::name_exclude_dirs))
+                    .align_left(),
+            )
             .with_child(Label::new("Content Settings").align_left().padding(10.))
             .with_child(Checkbox::new("Case sensitive").lens(AppState::content_case_sensitive).align_left())
             .with_child(Checkbox::new("Extended file types").lens(AppState::content_extended).align_left())
             .with_child(Checkbox::new("Literal match (non regex)").lens(AppState::content_nonregex).align_left())
+            .with_child(Checkbox::new("Multiline").lens(AppState::content_multiline).align_left())
+            .with_child(Checkbox::new("Index contents (faster repeated searches)").lens(AppState::content_use_index).align_left())
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Context lines before"))
+                    .with_child(context_box().lens(AppState::content_before_context))
+                    .with_child(Label::new("after"))
+                    .with_child(context_box().lens(AppState::content_after_context))
+                    .align_left(),
+            )
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Max lines per file"))
+                    .with_child(filter_box("", 50.).lens(AppState::content_max_count))
+                    .with_child(Label::new("Max line length"))
+                    .with_child(filter_box("", 50.).lens(AppState::content_max_line_len))
+                    .align_left(),
+            )
+            .with_child(Label::new("Replace Settings").align_left().padding(10.))
+            .with_child(Checkbox::new("Backup files before replacing").lens(AppState::replace_backup).align_left())
+            .with_child(Label::new("Result Settings").align_left().padding(10.))
+            .with_child(Checkbox::new("Watch for changes after search").lens(AppState::watch).align_left())
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Max results"))
+                    .with_child(filter_box("", 80.).lens(AppState::max_results))
+                    .align_left(),
+            )
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Open with"))
+                    .with_child(filter_box("code -g {path}:{line}", 300.).lens(AppState::editor))
+                    .with_child(Label::new("(double click a result, or its icon for the folder)"))
+                    .align_left(),
+            )
+            .with_child(Label::new("Saved Searches").align_left().padding(10.))
+            .with_child(
+                Flex::row()
+                    .with_child(filter_box("Name", 200.).lens(AppState::saved_name))
+                    .with_child(Button::new("Save search").on_click(|ctx, _data, _env| ctx.submit_command(SAVESEARCH)))
+                    .align_left(),
+            )
+            .with_child(
+                List::new(|| {
+                    Flex::row()
+                        .with_child(
+                            Button::dynamic(|name: &String, _env| name.clone())
+                                .on_click(|ctx, name: &mut String, _env| ctx.submit_command(LOADSEARCH.with(name.clone()))),
+                        )
+                        .with_child(Button::new("🗑").on_click(|ctx, name: &mut String, _env| ctx.submit_command(REMOVESEARCH.with(name.clone()))))
+                })
+                .lens(AppState::saved_names)
+                .align_left(),
+            )
             .padding(10.),
         Flex::column(),
     )
@@ -279,14 +504,104 @@ fn settings_panel() -> impl Widget<AppState> {
     .padding(10.)
 }
 
+///sets the options from the settings
+fn options_from_state(data: &AppState, ops: &mut Options) {
+    ops.name.case_sensitive = data.name_case_sensitive;
+    ops.name.fuzzy = data.name_fuzzy;
+    //there is no sort setting, so fuzzy results are always by relevance
+    ops.sort = match (data.name_fuzzy, ops.sort) {
+        (true, _) => Sort::Relevance,
+        (false, Sort::Relevance) => Sort::None,
+        (false, sort) => sort,
+    };
+    ops.name.follow_links = data.name_follow_links;
+    ops.name.same_filesystem = data.name_same_filesystem;
+    ops.content.case_sensitive = data.content_case_sensitive;
+    ops.content.extended = data.content_extended;
+    ops.content.nonregex = data.content_nonregex;
+    ops.content.multiline = data.content_multiline;
+    ops.content.use_index = data.content_use_index;
+    ops.content.before_context = data.content_before_context;
+    ops.content.after_context = data.content_after_context;
+    ops.content.max_count = data.content_max_count.trim().parse().ok();
+    ops.content.max_line_len = data.content_max_line_len.trim().parse().ok();
+    ops.max_results = data.max_results.trim().parse().ok();
+    ops.name.ignore_dot = data.name_ignore_dot;
+    ops.name.use_gitignore = data.name_use_gitignore;
+    ops.name.size = data.name_size.clone();
+    ops.name.modified = data.name_modified.clone();
+    ops.name.created = data.name_created.clone();
+    ops.name.globs = data.name_globs.split_whitespace().map(String::from).collect();
+    ops.name.types = data.name_types.split_whitespace().map(String::from).collect();
+    ops.name.min_depth = data.name_min_depth.trim().parse().ok();
+    ops.name.max_depth = data.name_max_depth.trim().parse().ok();
+    ops.name.exclude_dirs = data.name_exclude_dirs.split_whitespace().map(String::from).collect();
+    ops.name.file_types = data.name_search_file_type.clone().into();
+    ops.editor = data.editor.clone();
+}
+
+///shows the settings of the options, e.g. of a saved search
+fn state_from_options(data: &mut AppState, ops: &Options) {
+    data.name_case_sensitive = ops.name.case_sensitive;
+    data.name_fuzzy = ops.name.fuzzy;
+    data.name_same_filesystem = ops.name.same_filesystem;
+    data.name_follow_links = ops.name.follow_links;
+    data.name_ignore_dot = ops.name.ignore_dot;
+    data.name_use_gitignore = ops.name.use_gitignore;
+    data.name_size = ops.name.size.clone();
+    data.name_modified = ops.name.modified.clone();
+    data.name_created = ops.name.created.clone();
+    data.name_globs = ops.name.globs.join(" ");
+    data.name_types = ops.name.types.join(" ");
+    data.name_min_depth = ops.name.min_depth.map(|d| d.to_string()).unwrap_or_default();
+    data.name_max_depth = ops.name.max_depth.map(|d| d.to_string()).unwrap_or_default();
+    data.name_exclude_dirs = ops.name.exclude_dirs.join(" ");
+    data.name_search_file_type = ops.name.file_types.into();
+    data.content_case_sensitive = ops.content.case_sensitive;
+    data.content_extended = ops.content.extended;
+    data.content_nonregex = ops.content.nonregex;
+    data.content_multiline = ops.content.multiline;
+    data.content_use_index = ops.content.use_index;
+    data.content_before_context = ops.content.before_context;
+    data.content_after_context = ops.content.after_context;
+    data.content_max_count = ops.content.max_count.map(|d| d.to_string()).unwrap_or_default();
+    data.content_max_line_len = ops.content.max_line_len.map(|d| d.to_string()).unwrap_or_default();
+}
+
+fn state_search(data: &AppState) -> Search {
+    Search {
+        name_text: data.text_name.clone(),
+        contents_text: data.text_contents.clone(),
+        //there are no boxes for terms, they come from a loaded saved search
+        terms: data.terms.to_vec(),
+        dirs: Search::split_dirs(&data.dir),
+    }
+}
+
+fn saved_names(ops: &Options) -> Vector<String> {
+    ops.saved_searches.iter().map(|x| x.name.clone()).collect()
+}
+
+fn filter_box(placeholder: &str, width: f64) -> impl Widget<String> {
+    TextBox::new().with_placeholder(placeholder).fix_width(width).padding(5.)
+}
+
+fn context_box() -> impl Widget<usize> {
+    TextBox::new()
+        .with_formatter(ParseFormatter::new())
+        .update_data_while_editing(true)
+        .fix_width(50.)
+        .padding(5.)
+}
+
 //on enter
 pub struct TextBoxController;
 impl Controller<String, TextBox<String>> for TextBoxController {
     fn event(&mut self, child: &mut TextBox<String>, ctx: &mut EventCtx, event: &Event, data: &mut String, env: &Env) {
-        if let Event::KeyDown(key) = event {
-            if key.code == Code::Enter || key.code == Code::NumpadEnter {
-                ctx.submit_command(SEARCH);
-            }
+        if let Event::KeyDown(key) = event
+            && (key.code == Code::Enter || key.code == Code::NumpadEnter)
+        {
+            ctx.submit_command(SEARCH);
         }
         child.event(ctx, event, data, env)
     }
@@ -311,7 +626,7 @@ impl AppDelegate<AppState> for Delegate {
         }
         if cmd.is(SEARCH) {
             //early exit if invalid dir
-            if !self.manager.dir_is_valid(&data.dir) {
+            if !self.manager.dirs_are_valid(&Search::split_dirs(&data.dir)) {
                 data.message = rich("Invalid directory", Color::rgb8(200, 100, 100));
                 return Handled::Yes;
             }
@@ -321,45 +636,80 @@ impl AppDelegate<AppState> for Delegate {
                 data.message = rich("Nothing to search for", Color::rgb8(200, 100, 100));
                 return Handled::Yes;
             }
+            //checked before the last results are cleared
+            let mut ops = self.manager.get_options();
+            options_from_state(data, &mut ops);
+            let valid = Manager::validate(&state_search(data), &ops);
+            show_pattern_error(data, valid.as_ref().err());
+            if valid.is_err() {
+                return Handled::Yes;
+            }
             data.find_name = String::from("Stop");
 
             data.visible.clear();
             data.visible_errors.clear();
+            data.search_errors.clear();
 
             data.data.clear();
             data.done = false;
+            data.can_apply = false;
+            data.replace_preview = Arc::new(None);
             data.interim_count = 0;
             data.searched_count = 0;
 
             data.re_name = RegexBuilder::new(&data.text_name).case_insensitive(!data.name_case_sensitive).build();
-            let mut pattern = data.text_contents.to_string();
-            if data.content_nonregex {
-                pattern = regex::escape(&pattern);
-            }
-            data.re_content = RegexBuilder::new(&pattern).case_insensitive(!data.content_case_sensitive).build();
 
             data.message = rich("Searching...", Color::YELLOW);
             data.error_message = String::new();
+            data.searched = Arc::new(Some((state_search(data), ops.content.clone())));
+            data.shown_results = ops.shown_results();
             //set options
-            let mut ops = self.manager.get_options();
-            ops.name.case_sensitive = data.name_case_sensitive;
-            ops.name.follow_links = data.name_follow_links;
-            ops.name.same_filesystem = data.name_same_filesystem;
-            ops.content.case_sensitive = data.content_case_sensitive;
-            ops.content.extended = data.content_extended;
-            ops.content.nonregex = data.content_nonregex;
-            ops.name.ignore_dot = data.name_ignore_dot;
-            ops.name.use_gitignore = data.name_use_gitignore;
-            ops.name.file_types = data.name_search_file_type.clone().into();
-
             self.manager.set_options(ops);
+            if !data.watch {
+                self.manager.unwatch();
+            }
 
             data.start = Instant::now();
-            self.manager.search(&Search {
-                name_text: data.text_name.clone(),
-                contents_text: data.text_contents.clone(),
-                dir: data.dir.clone(),
-            });
+            if let Err(err) = self.manager.search(&state_search(data)) {
+                show_pattern_error(data, Some(&err));
+                data.find_name = String::from("Find");
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(SAVESEARCH) {
+            let name = data.saved_name.trim().to_string();
+            if name.is_empty() {
+                data.message = rich("Name the search to save it", Color::rgb8(200, 100, 100));
+                return Handled::Yes;
+            }
+            let mut ops = self.manager.get_options();
+            options_from_state(data, &mut ops);
+            self.manager.save_search(SavedSearch::new(&name, &state_search(data), &ops));
+            data.saved_names = saved_names(&self.manager.get_options());
+            data.message = rich(&format!("Saved search {name}"), Color::GREEN);
+            return Handled::Yes;
+        }
+        if let Some(name) = cmd.get(LOADSEARCH) {
+            let Some(search) = self.manager.load_saved_search(name) else {
+                return Handled::Yes;
+            };
+            state_from_options(data, &self.manager.get_options());
+            data.text_name = search.name_text.clone();
+            data.text_contents = search.contents_text.clone();
+            data.dir = Search::join_dirs(&search.dirs);
+            data.saved_name = name.clone();
+            let mut string = format!("Loaded search {name}");
+            if !search.terms.is_empty() {
+                string += &format!(" with {} more content terms", search.terms.len());
+            }
+            data.terms = Arc::new(search.terms);
+            data.message = rich(&string, Color::GREEN);
+            return Handled::Yes;
+        }
+        if let Some(name) = cmd.get(REMOVESEARCH) {
+            self.manager.remove_saved_search(name);
+            data.saved_names = saved_names(&self.manager.get_options());
+            data.message = rich(&format!("Removed search {name}"), Color::YELLOW);
             return Handled::Yes;
         }
 
@@ -368,6 +718,69 @@ impl AppDelegate<AppState> for Delegate {
             return Handled::Yes;
         }
 
+        if cmd.is(REPLACEPREVIEW) {
+            let (Some(results), Some((search, content_ops))) = (&*data.raw_data, &*data.searched) else {
+                data.message = rich("Search contents before replacing", Color::rgb8(200, 100, 100));
+                return Handled::Yes;
+            };
+            //the pattern and options the results were searched with, not what the boxes hold now. Without contents, the first term
+            let Some(term) = search.content_terms().into_iter().find(|x| x.occur != Occur::MustNot) else {
+                data.message = rich("Nothing to replace", Color::rgb8(200, 100, 100));
+                return Handled::Yes;
+            };
+            match replace::preview(results, &term.pattern, &data.text_replace, content_ops) {
+                Ok(preview) => {
+                    data.visible.clear();
+                    for file in preview.files.iter().take(data.shown_results) {
+                        data.visible.push_back(highlight_replacement(file, 100));
+                    }
+                    add_errors(data, preview.errors.iter().map(|a| SearchError::other(None, a.clone())));
+                    data.message = rich(
+                        &format!(
+                            "Preview of {} replacements in {} files. Apply to write them",
+                            preview.change_count(),
+                            preview.files.len()
+                        ),
+                        Color::YELLOW,
+                    );
+                    data.can_apply = !preview.files.is_empty();
+                    data.replace_preview = Arc::new(Some(preview));
+                }
+                Err(err) => data.message = rich(&format!("Invalid pattern: {err}"), Color::rgb8(200, 100, 100)),
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(REPLACEAPPLY) {
+            if let Some(preview) = &*data.replace_preview {
+                let applied = replace::apply(preview, data.replace_backup);
+                let mut string = format!("Replaced in {} files", applied.files.len());
+                if !applied.errors.is_empty() {
+                    string += &format!(" with {} errors", applied.errors.len());
+                    add_errors(data, applied.errors.iter().map(|a| SearchError::other(None, a.clone())));
+                }
+                data.message = RichText::new(string.into());
+                data.can_undo = !applied.backups.is_empty();
+                data.replace_applied = Arc::new(Some(applied));
+            }
+            data.can_apply = false;
+            data.replace_preview = Arc::new(None);
+            return Handled::Yes;
+        }
+        if cmd.is(REPLACEUNDO) {
+            if let Some(applied) = &*data.replace_applied {
+                let errors = replace::undo(applied);
+                let mut string = format!("Restored {} files", applied.backups.len() - errors.len());
+                if !errors.is_empty() {
+                    string += &format!(" with {} errors", errors.len());
+                    add_errors(data, errors.into_iter().map(|a| SearchError::other(None, a)));
+                }
+                data.message = RichText::new(string.into());
+            }
+            data.can_undo = false;
+            data.replace_applied = Arc::new(None);
+            return Handled::Yes;
+        }
+
         if cmd.is(EXPORT) {
             let data = data.data.iter().map(|x| x.to_string()).collect::<Vec<_>>().join("\n");
             Application::global().clipboard().put_string(&data);
@@ -375,8 +788,52 @@ impl AppDelegate<AppState> for Delegate {
             ctx.submit_command(Command::new(UPDATEMESSAGE, "Copied to clipboard".to_string(), Target::Auto));
             return Handled::Yes;
         }
+        if cmd.is(SAVE) {
+            let Some(results) = &*data.raw_data else {
+                data.message = rich("Nothing to save", Color::rgb8(200, 100, 100));
+                return Handled::Yes;
+            };
+            //the format is taken from the extension
+            let Some(file) = rfd::FileDialog::new()
+                .add_filter("JSON", &["json"])
+                .add_filter("CSV", &["csv"])
+                .add_filter("ripgrep JSON Lines", &["jsonl"])
+                .add_filter("Paths", &["txt"])
+                .set_file_name("results.json")
+                .save_file()
+            else {
+                return Handled::Yes;
+            };
+            data.message = match export::save(results, &file) {
+                Ok(()) => rich(&format!("Saved {}", file.display()), Color::GREEN),
+                Err(err) => rich(&err, Color::rgb8(200, 100, 100)),
+            };
+            return Handled::Yes;
+        }
+        if let Some(kind) = cmd.get(TOGGLEERRORS) {
+            if !data.hidden_errors.remove(kind) {
+                data.hidden_errors.insert(*kind);
+            }
+            data.visible_errors = error_rows(&data.search_errors, &data.hidden_errors);
+            return Handled::Yes;
+        }
+        if let Some(target) = cmd.get(RESULTCLICKED) {
+            //a second click on the same part of a result opens it, otherwise its path is copied
+            let double = matches!(&data.last_click, Some((time, last)) if last == target && time.elapsed() < DOUBLE_CLICK);
+            if double {
+                data.last_click = None;
+                data.message = match target.open(&data.editor) {
+                    Ok(()) => rich(&format!("Opened {}", target.path()), Color::GREEN),
+                    Err(err) => rich(&err, Color::rgb8(200, 100, 100)),
+                };
+            } else {
+                data.last_click = Some((Instant::now(), target.clone()));
+                ctx.submit_command(EXPORTSINGLE.with(target.path().to_string()));
+            }
+            return Handled::Yes;
+        }
         if let Some(line) = cmd.get(EXPORTSINGLE) {
-            Application::global().clipboard().put_string(&line);
+            Application::global().clipboard().put_string(line);
 
             ctx.submit_command(Command::new(UPDATEMESSAGE, "Copied to clipboard".to_string(), Target::Auto));
 
@@ -415,7 +872,7 @@ impl AppDelegate<AppState> for Delegate {
                     if filecount > 0 && foldercount > 0 {
                         string += &format!(" {} total", filecount + foldercount);
                     }
-                    let line_count = results.data.iter().map(|x| x.matches.len()).sum::<usize>();
+                    let line_count = results.data.iter().map(|x| x.match_count()).sum::<usize>();
                     if line_count > 0 {
                         string += &format!(" with {} lines", line_count);
                     }
@@ -425,10 +882,13 @@ impl AppDelegate<AppState> for Delegate {
                     if results.stopped {
                         string += " (stopped)";
                     }
+                    if results.truncated {
+                        string += " (max results reached)";
+                    }
 
-                    if !data.visible_errors.is_empty() {
-                        let mut str = format!("{} error", data.visible_errors.len());
-                        if data.visible_errors.len() > 1 {
+                    if !data.search_errors.is_empty() {
+                        let mut str = format!("{} error", data.search_errors.len());
+                        if data.search_errors.len() > 1 {
                             str.push('s');
                         }
                         data.error_message = str;
@@ -439,14 +899,32 @@ impl AppDelegate<AppState> for Delegate {
 
                     if data.visible.len() != results.data.len() {
                         data.visible.clear();
-                        for fi in results.data.iter().take(MAX_NAMES) {
-                            data.visible.push_back(highlight_result(fi, &data.re_content, &data.re_line, 100));
+                        for fi in results.data.iter().take(data.shown_results) {
+                            data.visible.push_back(highlight_result(fi, &data.re_line, 100));
+                        }
+                    }
+
+                    if data.watch
+                        && !self.manager.is_watching()
+                        && let Err(err) = self.manager.watch()
+                    {
+                        add_errors(data, [SearchError::other(None, err)]);
+                    }
+                }
+                SearchResult::Watch(event) => {
+                    if let Some(results) = Arc::make_mut(&mut data.raw_data) {
+                        results.apply(event);
+                        data.data = results.data.iter().map(|x| x.path.to_string()).collect();
+                        data.visible.clear();
+                        for fi in results.data.iter().take(data.shown_results) {
+                            data.visible.push_back(highlight_result(fi, &data.re_line, 100));
                         }
                     }
+                    data.message = rich(&format!("{event} (watching)"), Color::rgb8(100, 200, 100));
                 }
                 SearchResult::InterimResult(fi) => {
-                    if data.visible.len() < MAX_NAMES {
-                        data.visible.push_back(highlight_result(fi, &data.re_content, &data.re_line, 100));
+                    if data.visible.len() < data.shown_results {
+                        data.visible.push_back(highlight_result(fi, &data.re_line, 100));
                     }
                     data.interim_count += 1;
 
@@ -458,7 +936,8 @@ impl AppDelegate<AppState> for Delegate {
                         data.last_update = Instant::now();
                     }
                 }
-                SearchResult::SearchErrors(errs) => data.visible_errors.extend(errs.iter().map(|a| rich(a, Color::WHITE))),
+                //like rg, binary files are skipped quietly
+                SearchResult::SearchErrors(errs) => add_errors(data, errs.iter().filter(|x| x.kind() != ErrorKind::Binary).cloned()),
                 SearchResult::SearchCount(count) => {
                     if !data.done {
                         data.searched_count = *count;
@@ -524,25 +1003,26 @@ impl AppDelegate<AppState> for Delegate {
     }
 }
 
-fn highlight_result(
-    x: &FileInfo,
-    re_content: &Result<Regex, regex::Error>,
-    re_numbers: &Result<Regex, regex::Error>,
-    max_content_count: usize,
-) -> RichText {
+fn highlight_result(x: &FileInfo, re_numbers: &Result<Regex, regex::Error>, max_content_count: usize) -> RichText {
     let sym = if x.is_folder { "📁" } else { "📝" };
-    let symlen = sym.as_bytes().len();
+    let symlen = sym.len();
     let mut full = sym.to_string();
     const MAX_LEN: usize = 400;
-    let content = x.content(max_content_count, MAX_LEN);
+    let all_lines = x.unique_lines();
+    let lines = match_lines(x, &all_lines[..all_lines.len().min(max_content_count)]);
+    let content = lines
+        .iter()
+        .map(|(mat, _)| FileInfo::limited_match(mat, MAX_LEN, true))
+        .collect::<Vec<String>>()
+        .join("\n");
     let mut content_with_extra = content.clone();
-    if !content.is_empty() && x.matches.len() > max_content_count {
-        content_with_extra.push_str(&format!("\nand {} other lines", x.matches.len() - max_content_count));
+    if !content.is_empty() && all_lines.len() > max_content_count {
+        content_with_extra.push_str(&format!("\nand {} other lines", all_lines.len() - max_content_count));
     }
     full.push(' ');
     full.push_str(&x.path);
-    let plugin = if let Some(plug) = x.plugin {
-        format!(" ({})", plug.name())
+    let plugin = if let Some(plug) = &x.plugin {
+        format!(" ({plug})")
     } else {
         String::new()
     };
@@ -551,12 +1031,12 @@ fn highlight_result(
         let start = full.len();
         full.push('\n');
         full.push_str(&content_with_extra);
-        let mut rich = rich_with_path(&full, &x.path, Color::rgb8(58, 150, 221));
+        let mut rich = rich_with_links(&full, x, symlen, &lines, MAX_LEN);
         rich.add_attribute(start..full.len(), Attribute::text_color(Color::rgb8(164, 164, 164)));
         rich.add_attribute(0..symlen, Attribute::FontFamily(FontFamily::MONOSPACE));
         rich
     } else {
-        rich_with_path(&full, &x.path, Color::rgb8(58, 150, 221))
+        rich_with_links(&full, x, symlen, &[], MAX_LEN)
     };
     //highlight matches in name:
     let start = x.path.len() - x.name.len();
@@ -574,25 +1054,27 @@ fn highlight_result(
             eprintln!("{range:?} is out of range of {}", x.path.len());
         }
     }
-    //highlight matches in content:
     if !x.matches.is_empty() {
-        if let Ok(re) = &re_content {
-            for cap in re.captures_iter(&content) {
-                if let Some(mat) = cap.get(0) {
-                    let mut range = mat.range();
-                    if range.end <= content.len() {
-                        range.start += x.path.len() + plugin.len() + symlen + 2;
-                        range.end += x.path.len() + plugin.len() + symlen + 2;
-                        if full.is_char_boundary(range.start) && full.is_char_boundary(range.end) {
-                            rich.add_attribute(range.clone(), Attribute::Weight(FontWeight::BOLD));
-                            rich.add_attribute(range.clone(), Attribute::text_color(Color::rgb8(189, 60, 71)));
-                        }
-                    } else {
-                        eprintln!("{range:?} is out of range of {}", content.len());
-                    }
+        //highlight matches of every term in content, and dim context lines
+        let mut line_start = x.path.len() + plugin.len() + symlen + 2;
+        for (mat, ranges) in lines.iter() {
+            let line_len = FileInfo::limited_match(mat, MAX_LEN, true).len();
+            if mat.is_context {
+                rich.add_attribute(line_start..line_start + line_len, Attribute::text_color(Color::rgb8(100, 100, 100)));
+            }
+            //the line number before the content, and the content after MAX_LEN is cut
+            let content_start = line_start + format!("{}: ", mat.line).len();
+            let shown = mat.content.char_indices().nth(MAX_LEN).map_or(mat.content.trim_end().len(), |(i, _)| i);
+            for range in ranges {
+                let range = content_start + range.start.min(shown)..content_start + range.end.min(shown);
+                if !range.is_empty() && full.is_char_boundary(range.start) && full.is_char_boundary(range.end) {
+                    rich.add_attribute(range.clone(), Attribute::Weight(FontWeight::BOLD));
+                    rich.add_attribute(range, Attribute::text_color(Color::rgb8(189, 60, 71)));
                 }
             }
+            line_start += line_len + 1;
         }
+
         //highlight plugin
         if !plugin.is_empty() {
             rich.add_attribute(
@@ -622,14 +1104,109 @@ fn highlight_result(
     rich
 }
 
+fn highlight_replacement(file: &FileReplacement, max_change_count: usize) -> RichText {
+    let mut builder = RichTextBuilder::new();
+    builder
+        .push(&format!("📝 {}", file.path))
+        .add_attr(Attribute::text_color(Color::rgb8(58, 150, 221)));
+    for change in file.changes.iter().take(max_change_count) {
+        builder
+            .push(&format!("\n- {}: {}", change.line, change.before))
+            .add_attr(Attribute::text_color(Color::rgb8(189, 60, 71)));
+        builder
+            .push(&format!("\n+ {}: {}", change.line, change.after))
+            .add_attr(Attribute::text_color(Color::rgb8(17, 122, 13)));
+    }
+    if file.changes.len() > max_change_count {
+        builder
+            .push(&format!("\nand {} other changes", file.changes.len() - max_change_count))
+            .add_attr(Attribute::text_color(Color::rgb8(164, 164, 164)));
+    }
+    builder.build()
+}
+
+///next to the box of the pattern, terms have no box so are shown with the contents
+fn show_pattern_error(data: &mut AppState, err: Option<&PatternError>) {
+    data.name_error.clear();
+    data.content_error.clear();
+    let Some(err) = err else {
+        return;
+    };
+    match err.field {
+        PatternField::Name => data.name_error = err.to_string(),
+        PatternField::Content | PatternField::Term(_) => data.content_error = err.to_string(),
+    }
+    data.message = rich("Invalid regex", Color::rgb8(200, 100, 100));
+}
+
+fn add_errors(data: &mut AppState, errs: impl IntoIterator<Item = SearchError>) {
+    data.search_errors.extend(errs);
+    data.visible_errors = error_rows(&data.search_errors, &data.hidden_errors);
+}
+
+///errors grouped by kind, each under a header that collapses the group when clicked
+fn error_rows(errors: &[SearchError], hidden: &HashSet<ErrorKind>) -> Vector<RichText> {
+    let mut groups: BTreeMap<ErrorKind, Vec<&SearchError>> = BTreeMap::new();
+    for err in errors {
+        groups.entry(err.kind()).or_default().push(err);
+    }
+    let mut rows = Vector::new();
+    for (kind, errs) in groups {
+        let collapsed = hidden.contains(&kind);
+        let mut builder = RichTextBuilder::new();
+        builder
+            .push(&format!("{} {kind} ({})", if collapsed { "▸" } else { "▾" }, errs.len()))
+            .add_attr(Attribute::text_color(Color::rgb8(58, 150, 221)))
+            .weight(FontWeight::BOLD)
+            .link(Command::new(TOGGLEERRORS, kind, Target::Auto));
+        rows.push_back(builder.build());
+        if !collapsed {
+            rows.extend(errs.iter().map(|x| rich(&x.to_string(), Color::WHITE)));
+        }
+    }
+    rows
+}
+
 fn rich(str: &str, col: Color) -> RichText {
     RichText::new(str.into()).with_attribute(.., Attribute::text_color(col))
 }
 
-fn rich_with_path(str: &str, path: &str, col: Color) -> RichText {
+///each line of the matches once, where several terms matched (or gave context to) it, with the ranges of all terms in it
+fn match_lines<'a>(x: &'a FileInfo, lines: &[&'a Match]) -> Vec<(&'a Match, Vec<std::ops::Range<usize>>)> {
+    lines
+        .iter()
+        .map(|line| {
+            let ranges = x
+                .matches
+                .iter()
+                .filter(|m| m.line == line.line && !m.is_context)
+                .flat_map(|m| m.ranges.iter().cloned())
+                .collect();
+            (*line, ranges)
+        })
+        .collect()
+}
+
+///`full` is the icon, the path, then the lines of the matches. The icon links to the folder,
+///each line to the file at the line, and the rest to the file
+fn rich_with_links(full: &str, x: &FileInfo, symlen: usize, lines: &[(&Match, Vec<std::ops::Range<usize>>)], max_len: usize) -> RichText {
     let mut builder = RichTextBuilder::new();
-    let command = Command::new(EXPORTSINGLE, path.to_string(), Target::Auto);
-    builder.push(str).add_attr(Attribute::text_color(col)).link(command);
+    let mut push = |text: &str, target: OpenTarget| {
+        if text.is_empty() {
+            return;
+        }
+        let command = Command::new(RESULTCLICKED, target, Target::Auto);
+        builder.push(text).add_attr(Attribute::text_color(Color::rgb8(58, 150, 221))).link(command);
+    };
+    push(&full[..symlen], OpenTarget::Folder(x.path.clone()));
+    let mut start = full.find('\n').unwrap_or(full.len());
+    push(&full[symlen..start], OpenTarget::File(x.path.clone(), None));
+    for (mat, _) in lines {
+        let end = (start + 1 + FileInfo::limited_match(mat, max_len, true).len()).min(full.len());
+        push(&full[start..end], OpenTarget::File(x.path.clone(), Some(mat.line)));
+        start = end;
+    }
+    push(&full[start..], OpenTarget::File(x.path.clone(), None));
     builder.build()
 }
 
@@ -640,6 +1217,16 @@ enum SearchFileType {
     Folders,
 }
 
+impl From<FTypes> for SearchFileType {
+    fn from(x: FTypes) -> Self {
+        match x {
+            FTypes::All => SearchFileType::All,
+            FTypes::Files => SearchFileType::Files,
+            FTypes::Directories => SearchFileType::Folders,
+        }
+    }
+}
+
 impl From<SearchFileType> for FTypes {
     fn from(x: SearchFileType) -> Self {
         match x {
@@ -3,7 +3,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::{
-    path::PathBuf,
+    collections::BTreeMap,
     process,
     sync::{Arc, Mutex},
     thread,
@@ -12,9 +12,13 @@ use std::{
 
 use eframe::egui::{self, Grid, ScrollArea, ViewportBuilder};
 use librusl::{
+    error::{ErrorKind, PatternError, PatternField, SearchError},
+    export,
     fileinfo::FileInfo,
     manager::{FinalResults, Manager, SearchResult},
-    search::Search,
+    open::OpenTarget,
+    options::SavedSearch,
+    search::{ContentTerm, Search},
 };
 
 pub fn main() {
@@ -44,13 +48,23 @@ pub struct AppState {
     search_name: String,
     search_content: String,
     show_settings: bool,
-    results: Arc<Mutex<FinalResults>>,
-    interim: Arc<Mutex<Vec<FileInfo>>>,
+    results: Arc<Mutex<FinalResults>>, //without the data, which is read a page at a time from the store of the manager
+    errors: Arc<Mutex<Vec<SearchError>>>,
     manager: Manager,
     message: String,
     last_id: usize,
+    saved_name: String,
+    terms: Vec<ContentTerm>, //there are no boxes for terms, they come from a loaded saved search
+    pattern_error: Option<PatternError>,
+    page: usize,
+    watch: bool,                       //keep the results live after a search
+    shown: Vec<FileInfo>,              //the page drawn, copied from the store so its lock is not held while drawing
+    shown_key: Option<(usize, usize)>, //changes of the store and the page, when it was copied
 }
 
+///results shown at once, more are on the next pages
+const PAGE_SIZE: usize = 1000;
+
 impl eframe::App for AppState {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, _storage: &mut dyn eframe::Storage) {
@@ -80,14 +94,15 @@ impl AppState {
             duration: Duration::from_secs(0),
             id: 0,
             stopped: false,
+            truncated: false,
         }));
-        let interim = Arc::new(Mutex::new(vec![]));
+        let errors = Arc::new(Mutex::new(vec![]));
         let results_thread = results.clone();
-        let interim_thread = interim.clone();
 
         let (sx, rx) = std::sync::mpsc::channel();
-        let manager = Manager::new(sx);
-        spawn_receiver(rx, results_thread, interim_thread, cc.egui_ctx.clone());
+        let mut manager = Manager::new(sx);
+        manager.set_shared_only(true);
+        spawn_receiver(rx, results_thread, errors.clone(), cc.egui_ctx.clone());
 
         Self {
             // Example stuff:
@@ -96,10 +111,17 @@ impl AppState {
 
             show_settings: false,
             results,
-            interim,
+            errors,
             manager,
             message: "Ready to search".to_string(),
             last_id: 0,
+            saved_name: String::new(),
+            terms: vec![],
+            pattern_error: None,
+            page: 0,
+            watch: false,
+            shown: vec![],
+            shown_key: None,
         }
     }
 
@@ -124,6 +146,7 @@ impl AppState {
             if sn.lost_focus() && sn.ctx.input(|i| i.key_pressed(egui::Key::Enter)) {
                 self.do_search()
             }
+            self.pattern_error_label(ui, |x| x == PatternField::Name);
 
             ui.label("");
             ui.label("File contents");
@@ -131,16 +154,19 @@ impl AppState {
             if se.lost_focus() && se.ctx.input(|i| i.key_pressed(egui::Key::Enter)) {
                 self.do_search()
             }
+            //terms have no box, so are shown with the contents
+            self.pattern_error_label(ui, |x| x != PatternField::Name);
 
             ui.label("");
-            ui.label("Directory");
+            ui.label("Directories");
             ui.horizontal(|ui| {
-                if ui.button("📁").clicked() {
-                    if let Some(folder) = rfd::FileDialog::new().pick_folder() {
-                        let mut ops = self.manager.get_options();
-                        ops.last_dir = folder.to_string_lossy().to_string();
-                        self.manager.set_options(ops);
-                    }
+                if ui.button("📁").clicked()
+                    && let Some(folders) = rfd::FileDialog::new().pick_folders()
+                {
+                    let mut ops = self.manager.get_options();
+                    let dirs: Vec<String> = folders.iter().map(|x| x.to_string_lossy().to_string()).collect();
+                    ops.last_dir = Search::join_dirs(&dirs);
+                    self.manager.set_options(ops);
                 }
                 ui.text_edit_singleline(&mut self.manager.get_options().last_dir);
             });
@@ -157,31 +183,134 @@ impl AppState {
                 .export(self.results.lock().unwrap().data.iter().map(|x| x.path.to_string()).collect());
                 */
             }
+            if ui.button("Save").on_hover_text("Save to json, csv or jsonl (ripgrep) file").clicked() {
+                self.save_results();
+            }
             if ui
                 .button((if self.show_settings { "Show results" } else { "Show settings" }).to_string())
                 .clicked()
             {
                 self.show_settings = !self.show_settings;
             }
+            ui.add_space(20.);
+            self.saved_searches(ui);
             ui.add_space(40.);
             ui.separator();
             ui.hyperlink("https://github.com/griccardos/rusl");
         });
     }
 
+    fn saved_searches(&mut self, ui: &mut egui::Ui) {
+        ui.label("Saved searches");
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.saved_name).desired_width(120.).hint_text("Name"));
+            if ui.button("Save").on_hover_text("Save the search and settings").clicked() {
+                self.save_search();
+            }
+        });
+        for saved in self.manager.get_options().saved_searches {
+            ui.horizontal(|ui| {
+                if ui.button(&saved.name).clicked() {
+                    self.load_search(&saved.name);
+                }
+                if ui.button("🗑").on_hover_text("Remove").clicked() {
+                    self.manager.remove_saved_search(&saved.name);
+                }
+            });
+        }
+    }
+
+    fn save_search(&mut self) {
+        let name = self.saved_name.trim();
+        if name.is_empty() {
+            self.message = "Name the search to save it".to_string();
+            return;
+        }
+        self.manager
+            .save_search(SavedSearch::new(name, &self.search(), &self.manager.get_options()));
+        self.message = format!("Saved search {name}");
+    }
+
+    fn load_search(&mut self, name: &str) {
+        let Some(search) = self.manager.load_saved_search(name) else {
+            return;
+        };
+        let mut ops = self.manager.get_options();
+        ops.last_dir = Search::join_dirs(&search.dirs);
+        self.manager.set_options(ops);
+        self.search_name = search.name_text;
+        self.search_content = search.contents_text;
+        self.terms = search.terms;
+        self.saved_name = name.to_string();
+        self.message = format!("Loaded search {name}");
+    }
+
+    fn search(&self) -> Search {
+        Search {
+            name_text: self.search_name.clone(),
+            contents_text: self.search_content.clone(),
+            terms: self.terms.clone(),
+            dirs: Search::split_dirs(&self.manager.get_options().last_dir),
+        }
+    }
+
+    fn save_results(&mut self) {
+        //the format is taken from the extension
+        let file = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .add_filter("CSV", &["csv"])
+            .add_filter("ripgrep JSON Lines", &["jsonl"])
+            .add_filter("Paths", &["txt"])
+            .set_file_name("results.json")
+            .save_file();
+        if let Some(file) = file {
+            let results = {
+                let meta = self.results.lock().unwrap();
+                FinalResults {
+                    data: self.manager.results().read().unwrap().iter().cloned().collect(),
+                    duration: meta.duration,
+                    id: meta.id,
+                    stopped: meta.stopped,
+                    truncated: meta.truncated,
+                }
+            };
+            self.message = match export::save(&results, &file) {
+                Ok(()) => format!("Saved {}", file.display()),
+                Err(err) => err,
+            };
+        }
+    }
+
     fn do_search(&mut self) {
         if self.search_name.is_empty() && self.search_content.is_empty() {
             self.message = "Nothing to search for".to_string();
-        } else if !PathBuf::from(&self.manager.get_options().last_dir).exists() {
+        } else if !self.manager.dirs_are_valid(&Search::split_dirs(&self.manager.get_options().last_dir)) {
             self.message = "Invalid directory".to_string();
         } else {
-            self.interim.lock().unwrap().clear();
-            self.manager.search(&Search {
-                name_text: self.search_name.clone(),
-                contents_text: self.search_content.clone(),
-                dir: self.manager.get_options().last_dir,
-            });
-            self.message = "Searching...".to_string();
+            self.page = 0;
+            self.errors.lock().unwrap().clear();
+            if !self.watch {
+                self.manager.unwatch();
+            }
+            match self.manager.search(&self.search()) {
+                Ok(()) => {
+                    self.pattern_error = None;
+                    self.message = "Searching...".to_string();
+                }
+                Err(err) => {
+                    self.pattern_error = Some(err);
+                    self.message = "Invalid regex".to_string();
+                }
+            }
+        }
+    }
+
+    ///the invalid regex of the last search, under the box of its pattern
+    fn pattern_error_label(&self, ui: &mut egui::Ui, is_field: impl Fn(PatternField) -> bool) {
+        if let Some(err) = &self.pattern_error
+            && is_field(err.field)
+        {
+            ui.colored_label(egui::Color32::from_rgb(200, 100, 100), err.to_string());
         }
     }
     fn central_panel(&mut self, ctx: &egui::Context) {
@@ -203,51 +332,138 @@ impl AppState {
         ui.checkbox(&mut self.manager.get_options().name.follow_links, "Follow links");
         ui.label("Contents");
         ui.checkbox(&mut self.manager.get_options().content.case_sensitive, "Case sensitive");
+        ui.label("Results");
+        let mut ops = self.manager.get_options();
+        ui.horizontal(|ui| {
+            ui.label("Open with");
+            let editor = ui.add(egui::TextEdit::singleline(&mut ops.editor).hint_text("code -g {path}:{line}"));
+            if editor.changed() {
+                self.manager.set_options(ops.clone());
+            }
+        });
+        ui.checkbox(&mut self.watch, "Watch for changes after search");
     }
 
     fn results_panel(&mut self, ui: &mut egui::Ui) {
         ui.heading("Results");
+        self.errors_panel(ui);
+        let mut open = None;
+        let pages = {
+            let store = self.manager.results();
+            //only held to copy the page when it changed, so adding results does not wait for drawing
+            let store = store.read().unwrap();
+            if let Ok(results) = self.results.try_lock()
+                && store.is_done()
+                && results.id == store.id()
+                && results.id != self.last_id
+            {
+                self.last_id = results.id;
+                self.message = format!("Found {} results in {:.2}s", store.len(), results.duration.as_secs_f64());
+                if results.truncated {
+                    self.message += " (max results reached)";
+                }
+                if self.watch
+                    && !self.manager.is_watching()
+                    && let Err(err) = self.manager.watch()
+                {
+                    self.errors.lock().unwrap().push(SearchError::other(None, err));
+                }
+            }
+            let key = (store.changes(), self.page);
+            if self.shown_key != Some(key) {
+                self.shown = store.page(self.page, PAGE_SIZE).to_vec();
+                self.shown_key = Some(key);
+            }
+            store.page_count(PAGE_SIZE)
+        };
+        if pages > 1 {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(self.page > 0, egui::Button::new("Previous")).clicked() {
+                    self.page -= 1;
+                }
+                ui.label(format!("Page {} of {pages}", self.page + 1));
+                if ui.add_enabled(self.page + 1 < pages, egui::Button::new("Next")).clicked() {
+                    self.page += 1;
+                }
+            });
+        }
         ScrollArea::new([true, true]).min_scrolled_height(200.).show(ui, |ui| {
             Grid::new("grid").num_columns(2).striped(true).show(ui, |ui| {
-                if let Ok(results) = self.results.try_lock() {
-                    if !results.data.is_empty() {
-                        self.draw_fileinfos(&results.data, ui);
-                        if results.id != self.last_id {
-                            self.last_id = results.id;
-                            self.message = format!("Found {} results in {:.2}s", results.data.len(), results.duration.as_secs_f64());
-                        }
-                    } else if let Ok(interim) = self.interim.try_lock() {
-                        self.draw_fileinfos(&interim, ui);
-                    }
-                }
+                open = Self::draw_fileinfos(&self.shown, ui);
             });
         });
+        if let Some(target) = open {
+            self.message = match target.open(&self.manager.get_options().editor) {
+                Ok(()) => format!("Opened {}", target.path()),
+                Err(err) => err,
+            };
+        }
+    }
+
+    ///errors grouped by kind, each group collapsed until clicked
+    fn errors_panel(&self, ui: &mut egui::Ui) {
+        let Ok(errors) = self.errors.try_lock() else {
+            return;
+        };
+        let mut groups: BTreeMap<ErrorKind, Vec<&SearchError>> = BTreeMap::new();
+        for err in errors.iter() {
+            groups.entry(err.kind()).or_default().push(err);
+        }
+        for (kind, errs) in groups {
+            egui::CollapsingHeader::new(format!("{kind} ({})", errs.len())).show(ui, |ui| {
+                ScrollArea::vertical().id_salt(kind).max_height(150.).show(ui, |ui| {
+                    for err in errs {
+                        ui.label(err.to_string());
+                    }
+                });
+            });
+        }
     }
 
-    fn draw_fileinfos(&self, results: &[FileInfo], ui: &mut egui::Ui) {
-        for r in results.iter().take(2000) {
-            ui.label(&r.path);
+    ///returns what to open: a double clicked path or line, or the folder from the menu of a path
+    fn draw_fileinfos(results: &[FileInfo], ui: &mut egui::Ui) -> Option<OpenTarget> {
+        let mut open = None;
+        for r in results {
+            let path = ui.add(egui::Label::new(&r.path).sense(egui::Sense::click()));
+            if path.double_clicked() {
+                open = Some(OpenTarget::File(r.path.clone(), None));
+            }
+            path.context_menu(|ui| {
+                if ui.button("Open").clicked() {
+                    open = Some(OpenTarget::File(r.path.clone(), None));
+                    ui.close();
+                }
+                if ui.button("Open folder").clicked() {
+                    open = Some(OpenTarget::Folder(r.path.clone()));
+                    ui.close();
+                }
+            });
             const MAX_COUNT: usize = 100;
             const MAX_LEN: usize = 200;
-            let mut content = r.content(MAX_COUNT, MAX_LEN);
-            if r.matches.len() > MAX_COUNT {
-                content.push_str(&format!("\nand {} other lines", r.matches.len() - MAX_COUNT));
-            }
-            if !content.is_empty() {
-                ui.label(&content);
+            let lines = r.unique_lines();
+            if !lines.is_empty() {
+                ui.vertical(|ui| {
+                    for mat in lines.iter().take(MAX_COUNT) {
+                        let line = ui.add(egui::Label::new(FileInfo::limited_match(mat, MAX_LEN, true)).sense(egui::Sense::click()));
+                        if line.double_clicked() {
+                            open = Some(OpenTarget::File(r.path.clone(), Some(mat.line)));
+                        }
+                    }
+                    if lines.len() > MAX_COUNT {
+                        ui.label(format!("and {} other lines", lines.len() - MAX_COUNT));
+                    }
+                });
             }
             ui.end_row();
         }
-        if results.len() > 2000 {
-            ui.label(format!("and {} others...", results.len() - 2000));
-        }
+        open
     }
 }
 
 fn spawn_receiver(
     rx: std::sync::mpsc::Receiver<SearchResult>,
     results_thread: Arc<Mutex<FinalResults>>,
-    interim: Arc<Mutex<Vec<FileInfo>>>,
+    errors: Arc<Mutex<Vec<SearchError>>>,
     context: egui::Context,
 ) {
     thread::spawn(move || {
@@ -260,15 +476,24 @@ fn spawn_receiver(
                             res_self.data = res.data;
                             res_self.duration = res.duration;
                             res_self.id = res.id;
+                            res_self.truncated = res.truncated;
                             context.request_repaint();
                         }
                     }
-                    SearchResult::InterimResult(fi) => {
-                        //this is to show interim results as they are received
-                        interim.lock().unwrap().push(fi);
+                    SearchResult::InterimResult(_) => {
+                        //they are in the store of the manager, to show as they are received
+                        context.request_repaint();
+                    }
+                    SearchResult::SearchErrors(errs) => {
+                        //like rg, binary files are skipped quietly
+                        errors.lock().unwrap().extend(errs.into_iter().filter(|x| x.kind() != ErrorKind::Binary));
+                        context.request_repaint();
                     }
-                    SearchResult::SearchErrors(_) => { /*todo show errors*/ }
                     SearchResult::SearchCount(_) => {}
+                    SearchResult::Watch(_) => {
+                        //the manager applies them to its store
+                        context.request_repaint();
+                    }
                 },
                 Err(err) => {
                     eprintln!("Error: {err:?}:{err}")
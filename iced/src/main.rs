@@ -1,6 +1,7 @@
 //hide windows console
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 use std::{
+    collections::BTreeMap,
     sync::mpsc::{channel, Receiver},
     time::Duration,
 };
@@ -21,10 +22,13 @@ use iced::{
 
 //use iced_core::{text::Span, window};
 use librusl::{
+    error::{ErrorKind, PatternError, PatternField, SearchError},
+    export,
     fileinfo::FileInfo,
-    manager::{Manager, SearchResult},
-    options::FTypes,
-    search::Search,
+    manager::{FinalResults, Manager, SearchResult},
+    open::OpenTarget,
+    options::{FTypes, SavedSearch},
+    search::{ContentTerm, Search},
 };
 
 struct App {
@@ -32,12 +36,19 @@ struct App {
     contents: String,
     directory: String,
     results: Vec<FileInfo>,
+    final_results: Option<FinalResults>, //all the results of the last search, for saving
     manager: Manager,
     receiver: Receiver<SearchResult>,
     message: String,
     found: usize,
     searching: bool,
     show_settings: bool,
+    saved_name: String,
+    terms: Vec<ContentTerm>, //there are no boxes for terms, they come from a loaded saved search
+    errors: Vec<SearchError>,
+    shown_errors: Option<ErrorKind>, //the kind of errors listed, if any
+    pattern_error: Option<PatternError>,
+    watch: bool, //keep the results live after a search
 }
 
 #[derive(Debug, Clone)]
@@ -50,8 +61,16 @@ pub enum Message {
     CheckExternal,
     Event(iced::event::Event),
     CopyToClipboard(Vec<String>),
+    Open(OpenTarget),
+    EditorChanged(String),
     ToggleSettings,
     Settings(SettingsMessage),
+    SavedNameChanged(String),
+    SaveSearch,
+    LoadSearch(String),
+    RemoveSearch(String),
+    ShowErrors(ErrorKind),
+    SaveResults,
 }
 #[derive(Debug, Clone)]
 pub enum SettingsMessage {
@@ -64,6 +83,7 @@ pub enum SettingsMessage {
     ContentExtendedFiletypes,
     ContentLiteralMatch,
     NameType(FTypes),
+    Watch,
 }
 
 pub fn main() {
@@ -95,11 +115,18 @@ impl App {
             message: "".to_string(),
             directory: man.get_options().last_dir.clone(),
             results: vec![],
+            final_results: None,
             manager: man,
             receiver: r,
             found: 0,
             searching: false,
             show_settings: false,
+            saved_name: "".to_string(),
+            terms: vec![],
+            errors: vec![],
+            shown_errors: None,
+            pattern_error: None,
+            watch: false,
         };
         (d, focus_next())
     }
@@ -120,6 +147,10 @@ impl App {
                 Button::new(Text::new("Clipboard")).on_press(Message::CopyToClipboard(self.results.iter().map(|x| x.path.clone()).collect())),
             )
         };
+        let save = self
+            .final_results
+            .as_ref()
+            .map(|_| Button::new(Text::new("Save results")).on_press(Message::SaveResults));
         let dir = TextInput::new("", &self.directory).on_input(Message::DirectoryChanged).padding(4);
 
         let res = Column::with_children(
@@ -149,7 +180,7 @@ impl App {
                     if start < x.name.len() {
                         rts.push(span(&x.name[start..]));
                     }
-                    let rt = rich_text(rts);
+                    let rt = mouse_area(rich_text(rts)).on_double_click(Message::Open(OpenTarget::File(x.path.clone(), None)));
                     let icon = if x.path.starts_with("...") {
                         text!("")
                     } else if x.is_folder {
@@ -159,8 +190,12 @@ impl App {
                     }; //does not support unicode yet
 
                     let icon = tooltip(
-                        mouse_area(icon).on_press(Message::CopyToClipboard(vec![x.path.clone()])),
-                        container("Click to copy path to clipboard").padding(10).style(container::rounded_box),
+                        mouse_area(icon)
+                            .on_press(Message::CopyToClipboard(vec![x.path.clone()]))
+                            .on_double_click(Message::Open(OpenTarget::Folder(x.path.clone()))),
+                        container("Click to copy path to clipboard, double click to open the folder")
+                            .padding(10)
+                            .style(container::rounded_box),
                         tooltip::Position::Right,
                     );
 
@@ -169,7 +204,8 @@ impl App {
                     let mut col = Column::new().push(row);
 
                     //content matches
-                    for cline in x.matches.iter().take(max) {
+                    let lines = x.unique_lines();
+                    for cline in lines.iter().take(max) {
                         let mut cspans: Vec<Span> = vec![span(format!("{}: ", cline.line)).color(Color::from_rgb8(17, 122, 13))];
                         let mut last = 0;
                         //careful of char boudaries
@@ -188,11 +224,12 @@ impl App {
                             last = range.end;
                         }
                         cspans.push(span(text[last..].to_string()).color(Color::from_rgb8(200, 200, 200)));
-                        let content = rich_text(cspans);
+                        let content =
+                            mouse_area(rich_text(cspans)).on_double_click(Message::Open(OpenTarget::File(x.path.clone(), Some(cline.line))));
                         col = col.push(content);
                     }
-                    if x.matches.len() > max {
-                        col = col.push(Text::new(format!("... and {} more", x.matches.len() - max)).color(Color::from_rgb8(200, 200, 200)));
+                    if lines.len() > max {
+                        col = col.push(Text::new(format!("... and {} more", lines.len() - max)).color(Color::from_rgb8(200, 200, 200)));
                     }
                     // if !content.is_empty() {
                     //     let details = Text::new(content).width(Length::Fill).color(Color::from_rgb8(200, 200, 200));
@@ -205,6 +242,27 @@ impl App {
 
         let res = scrollable(res);
 
+        //a button for each kind of error, which lists them
+        let mut groups: BTreeMap<ErrorKind, Vec<&SearchError>> = BTreeMap::new();
+        for err in &self.errors {
+            groups.entry(err.kind()).or_default().push(err);
+        }
+        let mut errs = Column::new().push(
+            Row::with_children(groups.iter().map(|(kind, errs)| {
+                Button::new(Text::new(format!("{kind} ({})", errs.len())))
+                    .on_press(Message::ShowErrors(*kind))
+                    .into()
+            }))
+            .spacing(10),
+        );
+        if let Some(shown) = self.shown_errors.and_then(|x| groups.get(&x)) {
+            errs = errs.push(Column::with_children(
+                shown
+                    .iter()
+                    .map(|x| Text::new(x.to_string()).color(Color::from_rgb8(200, 200, 200)).into()),
+            ));
+        }
+
         let sets = if self.show_settings {
             let ops = self.manager.get_options();
             Some(
@@ -240,7 +298,36 @@ impl App {
                     .push(
                         checkbox("Literal match (non regex)", ops.content.nonregex)
                             .on_toggle(|_| Message::Settings(SettingsMessage::ContentLiteralMatch)),
-                    ),
+                    )
+                    .push(Text::new("Result settings"))
+                    .push(
+                        Row::new().spacing(10).push(Text::new("Open with")).push(
+                            TextInput::new("code -g {path}:{line}", &ops.editor)
+                                .on_input(Message::EditorChanged)
+                                .padding(4)
+                                .width(Length::Fixed(300.)),
+                        ),
+                    )
+                    .push(checkbox("Watch for changes after search", self.watch).on_toggle(|_| Message::Settings(SettingsMessage::Watch)))
+                    .push(Text::new("Saved searches"))
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .push(
+                                TextInput::new("Name", &self.saved_name)
+                                    .on_input(Message::SavedNameChanged)
+                                    .padding(4)
+                                    .width(Length::Fixed(200.)),
+                            )
+                            .push(Button::new(Text::new("Save search")).on_press(Message::SaveSearch)),
+                    )
+                    .push(Column::with_children(ops.saved_searches.iter().map(|x| {
+                        Row::new()
+                            .spacing(10)
+                            .push(Button::new(Text::new(x.name.clone())).on_press(Message::LoadSearch(x.name.clone())))
+                            .push(Button::new(Text::new("Remove")).on_press(Message::RemoveSearch(x.name.clone())))
+                            .into()
+                    }))),
             )
         } else {
             None
@@ -255,12 +342,15 @@ impl App {
                     .push(Space::new().width(Length::Fixed(10.)))
                     .push(name),
             )
+            .push(self.pattern_error(|x| x == PatternField::Name))
             .push(
                 Row::new()
                     .push(Text::new("Contents").width(Length::Fixed(100.)))
                     .push(Space::new().width(Length::Fixed(10.)))
                     .push(contents),
             )
+            //terms have no box, so are shown with the contents
+            .push(self.pattern_error(|x| x != PatternField::Name))
             .push(
                 Row::new()
                     .push(Text::new("Directory").width(Length::Fixed(100.)))
@@ -284,8 +374,10 @@ impl App {
                         Button::new(Text::new("Find")).on_press(Message::FindPressed)
                     })
                     .push(Text::new(&self.message))
-                    .push(clipboard),
+                    .push(clipboard)
+                    .push(save),
             )
+            .push(errs)
             .push(res)
             .into()
     }
@@ -303,54 +395,82 @@ impl App {
                     self.searching = false;
                 } else {
                     self.results.clear();
+                    self.final_results = None;
+                    self.errors.clear();
+                    self.shown_errors = None;
                     self.searching = true;
                     self.found = 0;
                     self.message = "Searching...".to_string();
-                    self.manager.search(&Search {
-                        dir: self.directory.clone(),
-                        name_text: self.name.clone(),
-                        contents_text: self.contents.clone(),
-                    })
+                    if !self.watch {
+                        self.manager.unwatch();
+                    }
+                    self.pattern_error = self.manager.search(&self.search()).err();
+                    if self.pattern_error.is_some() {
+                        self.searching = false;
+                        self.message = "Invalid regex".to_string();
+                    }
                 }
             }
             Message::NameChanged(nn) => self.name = nn,
             Message::ContentsChanged(con) => self.contents = con,
             Message::DirectoryChanged(dir) => {
                 self.directory = dir.clone();
-                if !self.manager.dir_is_valid(&dir) {
+                if !self.manager.dirs_are_valid(&Search::split_dirs(&dir)) {
                     self.message = "Invalid directory".to_string();
                 } else {
                     self.message = "".to_string();
                 }
             }
             Message::CheckExternal => {
+                let shown = self.manager.get_options().shown_results();
                 while let Ok(res) = self.receiver.try_recv() {
                     match res {
                         SearchResult::FinalResults(res) => {
                             self.searching = false;
                             self.message = format!("Found {} items in {:.2}s", res.data.len(), res.duration.as_secs_f64());
-                            //self.results = res.data.iter().take(1000).cloned().collect();
-                            if res.data.len() > 1000 {
+                            if res.truncated {
+                                self.message += " (max results reached)";
+                            }
+                            if res.data.len() > shown {
                                 self.results.push(FileInfo {
-                                    path: format!("...and {} others", res.data.len() - 1000),
+                                    path: format!("...and {} others", res.data.len() - shown),
+                                    root: "".into(),
                                     matches: vec![],
                                     ext: "".into(),
                                     name: "".into(),
                                     is_folder: false,
                                     plugin: None,
                                     ranges: vec![],
+                                    score: None,
+                                    size: None,
+                                    modified: None,
                                 });
                             }
+                            self.final_results = Some(res);
+                            if self.watch
+                                && !self.manager.is_watching()
+                                && let Err(err) = self.manager.watch()
+                            {
+                                self.errors.push(SearchError::other(None, err));
+                            }
                         }
                         SearchResult::InterimResult(res) => {
-                            if self.results.len() < 1000 {
+                            if self.results.len() < shown {
                                 self.results.push(res)
                             }
                             self.found += 1;
                             self.message = format!("Found {}, searching...", self.found);
                         }
-                        SearchResult::SearchErrors(_) => {}
+                        //like rg, binary files are skipped quietly
+                        SearchResult::SearchErrors(errs) => self.errors.extend(errs.into_iter().filter(|x| x.kind() != ErrorKind::Binary)),
                         SearchResult::SearchCount(_) => {}
+                        SearchResult::Watch(event) => {
+                            if let Some(results) = &mut self.final_results {
+                                results.apply(&event);
+                                self.results = results.data.iter().take(shown).cloned().collect();
+                            }
+                            self.message = format!("{event} (watching)");
+                        }
                     }
                 }
                 if let Err(std::sync::mpsc::TryRecvError::Disconnected) = self.receiver.try_recv() {
@@ -358,8 +478,9 @@ impl App {
                 }
             }
             Message::OpenDirectory => {
-                if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                    self.directory = path.to_string_lossy().to_string()
+                if let Some(paths) = rfd::FileDialog::new().pick_folders() {
+                    let dirs: Vec<String> = paths.iter().map(|x| x.to_string_lossy().to_string()).collect();
+                    self.directory = Search::join_dirs(&dirs)
                 }
             }
             Message::Event(iced::Event::Keyboard(Event::KeyPressed {
@@ -377,6 +498,17 @@ impl App {
                 // self.manager.export(str);
                 // self.message = "Copied to clipboard".to_string();
             }
+            Message::Open(target) => {
+                self.message = match target.open(&self.manager.get_options().editor) {
+                    Ok(()) => format!("Opened {}", target.path()),
+                    Err(err) => err,
+                };
+            }
+            Message::EditorChanged(editor) => {
+                let mut ops = self.manager.get_options();
+                ops.editor = editor;
+                self.manager.set_options(ops);
+            }
             Message::ToggleSettings => {
                 self.show_settings = !self.show_settings;
             }
@@ -390,17 +522,76 @@ impl App {
                     SettingsMessage::NameUseGitignore => ops.name.use_gitignore = !ops.name.use_gitignore,
                     SettingsMessage::NameFollowSymlinks => ops.name.follow_links = !ops.name.follow_links,
                     SettingsMessage::NameType(nt) => ops.name.file_types = nt,
+                    SettingsMessage::Watch => self.watch = !self.watch,
                     SettingsMessage::ContentLiteralMatch => ops.content.nonregex = !ops.content.nonregex,
                     SettingsMessage::ContentExtendedFiletypes => ops.content.extended = !ops.content.extended,
                 }
                 self.manager.set_options(ops);
             }
+            Message::SavedNameChanged(name) => self.saved_name = name,
+            Message::SaveSearch => {
+                let name = self.saved_name.trim();
+                if name.is_empty() {
+                    self.message = "Name the search to save it".to_string();
+                } else {
+                    self.manager
+                        .save_search(SavedSearch::new(name, &self.search(), &self.manager.get_options()));
+                    self.message = format!("Saved search {name}");
+                }
+            }
+            Message::LoadSearch(name) => {
+                if let Some(search) = self.manager.load_saved_search(&name) {
+                    self.directory = Search::join_dirs(&search.dirs);
+                    self.name = search.name_text;
+                    self.contents = search.contents_text;
+                    self.terms = search.terms;
+                    self.message = format!("Loaded search {name}");
+                    self.saved_name = name;
+                }
+            }
+            Message::RemoveSearch(name) => self.manager.remove_saved_search(&name),
+            Message::ShowErrors(kind) => self.shown_errors = if self.shown_errors == Some(kind) { None } else { Some(kind) },
+            Message::SaveResults => {
+                //the format is taken from the extension
+                let file = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .add_filter("CSV", &["csv"])
+                    .add_filter("ripgrep JSON Lines", &["jsonl"])
+                    .add_filter("Paths", &["txt"])
+                    .set_file_name("results.json")
+                    .save_file();
+                if let (Some(file), Some(results)) = (file, &self.final_results) {
+                    self.message = match export::save(results, &file) {
+                        Ok(()) => format!("Saved {}", file.display()),
+                        Err(err) => err,
+                    };
+                }
+            }
             Message::Event(_) => {}
         }
 
         Task::none()
     }
 
+    ///the invalid regex of the last search, under the box of its pattern
+    fn pattern_error(&self, is_field: impl Fn(PatternField) -> bool) -> Option<Row<'_, Message>> {
+        let err = self.pattern_error.as_ref().filter(|x| is_field(x.field))?;
+        Some(
+            Row::new()
+                .push(Space::new().width(Length::Fixed(110.)))
+                .push(Text::new(err.to_string()).color(Color::from_rgb8(200, 100, 100))),
+        )
+    }
+
+    fn search(&self) -> Search {
+        Search {
+            dirs: Search::split_dirs(&self.directory),
+            name_text: self.name.clone(),
+            contents_text: self.contents.clone(),
+            terms: self.terms.clone(),
+        }
+    }
+
     fn subscription(&self) -> iced::Subscription<Message> {
         Subscription::batch(vec![
             //keep looking for external messages.
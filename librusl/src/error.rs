@@ -0,0 +1,217 @@
+use std::{error::Error, fmt, io, ops::Range, path::Path, sync::Arc};
+
+type Source = Arc<dyn Error + Send + Sync>;
+
+///An error found while searching, with the path it is of and the error it came from
+#[derive(Clone, Debug)]
+pub enum SearchError {
+    PermissionDenied { path: String, source: Arc<io::Error> },
+    NotFound { path: String, source: Arc<io::Error> },
+    Binary { path: String }, //the search stopped at binary data, matches before it are kept
+    ExtractorFailed { path: String, extractor: String, source: Source },
+    InvalidRegex { pattern: String, source: Source },
+    SymlinkLoop { path: String, ancestor: String },
+    InvalidOptions { message: String }, //globs, file types, size and time filters
+    Other { path: Option<String>, source: Source },
+}
+
+///A pattern of a search that is not a valid regex, found by `Manager::search` before it searches
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternError {
+    pub field: PatternField,
+    pub pattern: String,
+    pub message: String,            //e.g. unclosed group
+    pub span: Option<Range<usize>>, //bytes of the pattern the error is at, if known
+}
+
+///Which pattern of a `Search` is invalid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatternField {
+    Name,
+    Content,
+    Term(usize), //index into `Search::terms`
+}
+
+///What kind a `SearchError` is, to group and filter errors by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorKind {
+    PermissionDenied,
+    NotFound,
+    Binary,
+    ExtractorFailed,
+    InvalidRegex,
+    SymlinkLoop,
+    InvalidOptions,
+    Other,
+}
+
+impl SearchError {
+    pub fn io(path: &Path, err: io::Error) -> Self {
+        let path = path.to_string_lossy().to_string();
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied { path, source: Arc::new(err) },
+            io::ErrorKind::NotFound => Self::NotFound { path, source: Arc::new(err) },
+            _ => Self::Other {
+                path: Some(path),
+                source: Arc::new(err),
+            },
+        }
+    }
+
+    ///from the walker, which wraps io errors with the path and depth
+    pub fn walk(err: ignore::Error) -> Self {
+        match err {
+            ignore::Error::WithPath { path, err } => match *err {
+                ignore::Error::Io(err) => Self::io(&path, err),
+                err => Self::Other {
+                    path: Some(path.to_string_lossy().to_string()),
+                    source: Arc::new(err),
+                },
+            },
+            ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => Self::walk(*err),
+            ignore::Error::Loop { ancestor, child } => Self::SymlinkLoop {
+                path: child.to_string_lossy().to_string(),
+                ancestor: ancestor.to_string_lossy().to_string(),
+            },
+            err => Self::Other {
+                path: None,
+                source: Arc::new(err),
+            },
+        }
+    }
+
+    ///e.g. could not save the index, or watch a directory
+    pub fn other(path: Option<&str>, message: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self::Other {
+            path: path.map(String::from),
+            source: Arc::from(message.into()),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::PermissionDenied { .. } => ErrorKind::PermissionDenied,
+            Self::NotFound { .. } => ErrorKind::NotFound,
+            Self::Binary { .. } => ErrorKind::Binary,
+            Self::ExtractorFailed { .. } => ErrorKind::ExtractorFailed,
+            Self::InvalidRegex { .. } => ErrorKind::InvalidRegex,
+            Self::SymlinkLoop { .. } => ErrorKind::SymlinkLoop,
+            Self::InvalidOptions { .. } => ErrorKind::InvalidOptions,
+            Self::Other { .. } => ErrorKind::Other,
+        }
+    }
+
+    ///the file or directory the error is of, if any
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::PermissionDenied { path, .. }
+            | Self::NotFound { path, .. }
+            | Self::Binary { path }
+            | Self::ExtractorFailed { path, .. }
+            | Self::SymlinkLoop { path, .. } => Some(path),
+            Self::Other { path, .. } => path.as_deref(),
+            Self::InvalidRegex { .. } | Self::InvalidOptions { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PermissionDenied { path, .. } => write!(f, "Permission denied: {path}"),
+            Self::NotFound { path, .. } => write!(f, "Not found: {path}"),
+            Self::Binary { path } => write!(f, "Binary file, search stopped: {path}"),
+            Self::ExtractorFailed { path, extractor, source } => write!(f, "Could not read {path} with {extractor}: {source}"),
+            Self::InvalidRegex { pattern, source } => write!(f, "Invalid regex {pattern}: {source}"),
+            Self::SymlinkLoop { path, ancestor } => write!(f, "Symlink loop: {path} links to {ancestor}"),
+            Self::InvalidOptions { message } => write!(f, "{message}"),
+            Self::Other { path: Some(path), source } => write!(f, "Could not read {path}: {source}"),
+            Self::Other { path: None, source } => write!(f, "{source}"),
+        }
+    }
+}
+
+impl Error for SearchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::PermissionDenied { source, .. } | Self::NotFound { source, .. } => Some(source.as_ref()),
+            Self::ExtractorFailed { source, .. } | Self::InvalidRegex { source, .. } | Self::Other { source, .. } => Some(source.as_ref()),
+            Self::Binary { .. } | Self::SymlinkLoop { .. } | Self::InvalidOptions { .. } => None,
+        }
+    }
+}
+
+impl PatternError {
+    pub fn new(field: PatternField, pattern: &str, err: &dyn fmt::Display) -> Self {
+        //the regex crates only have the position in their message, so the pattern is parsed again for it
+        let (message, span) = match regex_syntax::Parser::new().parse(pattern) {
+            Err(regex_syntax::Error::Parse(err)) => (err.kind().to_string(), Some(err.span().start.offset..err.span().end.offset)),
+            Err(regex_syntax::Error::Translate(err)) => (err.kind().to_string(), Some(err.span().start.offset..err.span().end.offset)),
+            _ => (err.to_string(), None),
+        };
+        Self {
+            field,
+            pattern: pattern.to_string(),
+            message,
+            span,
+        }
+    }
+
+    ///column of the pattern the error starts at, counting from 1
+    pub fn column(&self) -> Option<usize> {
+        let span = self.span.as_ref()?;
+        Some(self.pattern.get(..span.start).unwrap_or_default().chars().count() + 1)
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid regex {}: {}", self.pattern, self.message)?;
+        if let Some(column) = self.column() {
+            write!(f, " at column {column}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for PatternError {}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::PermissionDenied => "Permission denied",
+            Self::NotFound => "Not found",
+            Self::Binary => "Binary files",
+            Self::ExtractorFailed => "Extractor failed",
+            Self::InvalidRegex => "Invalid regex",
+            Self::SymlinkLoop => "Symlink loop",
+            Self::InvalidOptions => "Invalid options",
+            Self::Other => "Other",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds() {
+        let err = SearchError::io(Path::new("a.txt"), io::Error::from(io::ErrorKind::PermissionDenied));
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(err.path(), Some("a.txt"));
+        assert!(err.source().is_some());
+
+        let walk = ignore::Error::WithDepth {
+            depth: 2,
+            err: Box::new(ignore::Error::WithPath {
+                path: "dir/b.txt".into(),
+                err: Box::new(ignore::Error::Io(io::Error::from(io::ErrorKind::NotFound))),
+            }),
+        };
+        let err = SearchError::walk(walk);
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.path(), Some("dir/b.txt"));
+    }
+}
@@ -0,0 +1,200 @@
+use std::{fs, path::Path, time::Duration};
+
+use serde_json::{Value, json};
+
+use crate::{
+    fileinfo::{FileInfo, Match},
+    manager::FinalResults,
+};
+
+///Formats results can be exported in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Paths,  //one path per line, like the clipboard
+    Json,   //the whole `FinalResults`
+    Csv,    //a row per line of each file, or per file if it has no content matches
+    RgJson, //ripgrep's `--json` messages, one per line
+}
+
+impl ExportFormat {
+    ///from the extension of the file: json, csv, jsonl (ripgrep messages), otherwise paths
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().unwrap_or_default().to_string_lossy().to_lowercase().as_str() {
+            "json" => Self::Json,
+            "csv" => Self::Csv,
+            "jsonl" => Self::RgJson,
+            _ => Self::Paths,
+        }
+    }
+}
+
+pub fn export(results: &FinalResults, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Paths => results.data.iter().map(|x| x.path.as_str()).collect::<Vec<_>>().join("\n"),
+        ExportFormat::Json => serde_json::to_string_pretty(results).unwrap_or_default(),
+        ExportFormat::Csv => to_csv(&results.data),
+        ExportFormat::RgJson => to_rg_json(results),
+    }
+}
+
+///writes the results to a file, in the format of its extension (see `ExportFormat::from_path`)
+pub fn save(results: &FinalResults, path: &Path) -> Result<(), String> {
+    fs::write(path, export(results, ExportFormat::from_path(path))).map_err(|e| format!("Could not save {}: {e}", path.display()))
+}
+
+fn to_csv(files: &[FileInfo]) -> String {
+    let mut out = String::from("path,line,content,ranges,context\n");
+    for fi in files {
+        if fi.matches.is_empty() {
+            //ranges of the name
+            out.push_str(&csv_row(&[&fi.path, "", "", &ranges(&fi.ranges), ""]));
+        }
+        for mat in fi.matches.iter() {
+            out.push_str(&csv_row(&[
+                &fi.path,
+                &mat.line.to_string(),
+                &mat.content,
+                &ranges(&mat.ranges),
+                &mat.is_context.to_string(),
+            ]));
+        }
+    }
+    out
+}
+
+fn csv_row(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|x| {
+            if x.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", x.replace('"', "\"\""))
+            } else {
+                x.to_string()
+            }
+        })
+        .collect();
+    format!("{}\n", fields.join(","))
+}
+
+///e.g. `6..8;10..12`
+fn ranges(ranges: &[std::ops::Range<usize>]) -> String {
+    ranges.iter().map(|x| format!("{}..{}", x.start, x.end)).collect::<Vec<_>>().join(";")
+}
+
+///begin, match/context and end messages of each file, and a summary, as `rg --json` prints them.
+///Bytes searched are not known, so are 0
+fn to_rg_json(results: &FinalResults) -> String {
+    let mut lines = vec![];
+    let (mut with_match, mut matched_lines, mut matches) = (0, 0, 0);
+    for fi in results.data.iter() {
+        let path = json!({ "text": fi.path });
+        lines.push(json!({ "type": "begin", "data": { "path": path } }));
+        for mat in fi.matches.iter() {
+            lines.push(rg_match(&path, mat));
+        }
+        let file_lines = fi.matches.iter().filter(|x| !x.is_context).count();
+        let file_matches: usize = fi.matches.iter().filter(|x| !x.is_context).map(|x| x.ranges.len()).sum();
+        with_match += (file_lines > 0) as usize;
+        matched_lines += file_lines;
+        matches += file_matches;
+        lines.push(json!({
+            "type": "end",
+            "data": {
+                "path": path,
+                "binary_offset": null,
+                "stats": rg_stats(Duration::ZERO, 1, (file_lines > 0) as usize, file_lines, file_matches),
+            }
+        }));
+    }
+    lines.push(json!({
+        "type": "summary",
+        "data": {
+            "elapsed_total": rg_elapsed(results.duration),
+            "stats": rg_stats(results.duration, results.data.len(), with_match, matched_lines, matches),
+        }
+    }));
+    lines.iter().map(|x| format!("{x}\n")).collect()
+}
+
+fn rg_match(path: &Value, mat: &Match) -> Value {
+    let submatches: Vec<Value> = mat
+        .ranges
+        .iter()
+        .map(|x| json!({ "match": { "text": mat.content.get(x.clone()).unwrap_or_default() }, "start": x.start, "end": x.end }))
+        .collect();
+    json!({
+        "type": if mat.is_context { "context" } else { "match" },
+        "data": {
+            "path": path,
+            "lines": { "text": format!("{}\n", mat.content) },
+            "line_number": mat.line,
+            "absolute_offset": mat.offset,
+            "submatches": submatches,
+        }
+    })
+}
+
+fn rg_stats(elapsed: Duration, searches: usize, with_match: usize, matched_lines: usize, matches: usize) -> Value {
+    json!({
+        "elapsed": rg_elapsed(elapsed),
+        "searches": searches,
+        "searches_with_match": with_match,
+        "bytes_searched": 0,
+        "bytes_printed": 0,
+        "matched_lines": matched_lines,
+        "matches": matches,
+    })
+}
+
+fn rg_elapsed(elapsed: Duration) -> Value {
+    json!({
+        "secs": elapsed.as_secs(),
+        "nanos": elapsed.subsec_nanos(),
+        "human": format!("{:.6}s", elapsed.as_secs_f64()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn formats() {
+        let mat = Match {
+            line: 2,
+            end_line: 2,
+            offset: 6,
+            content: "there, \"41\" go".to_string(),
+            ranges: vec![8..10],
+            is_context: false,
+            term: 0,
+        };
+        let results = FinalResults {
+            data: vec![FileInfo {
+                matches: vec![mat],
+                ..FileInfo::fixture("dir/temp.csv", "dir")
+            }],
+            duration: Duration::from_millis(5),
+            id: 0,
+            stopped: false,
+            truncated: false,
+        };
+        assert_eq!(
+            export(&results, ExportFormat::Csv),
+            "path,line,content,ranges,context\ndir/temp.csv,2,\"there, \"\"41\"\" go\",8..10,false\n"
+        );
+
+        let json: Value = serde_json::from_str(&export(&results, ExportFormat::Json)).unwrap();
+        assert_eq!(json["data"][0]["matches"][0]["line"], 2);
+
+        let messages: Vec<Value> = export(&results, ExportFormat::RgJson)
+            .lines()
+            .map(|x| serde_json::from_str(x).unwrap())
+            .collect();
+        let types: Vec<&str> = messages.iter().map(|x| x["type"].as_str().unwrap()).collect();
+        assert_eq!(types, vec!["begin", "match", "end", "summary"]);
+        assert_eq!(messages[1]["data"]["submatches"][0]["match"]["text"], "41");
+        assert_eq!(messages[3]["data"]["stats"]["matches"], 1);
+    }
+}
@@ -1,17 +1,113 @@
 use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::fs::File;
+use std::io::Read;
 use std::panic::catch_unwind;
 use std::path::Path;
-pub trait ExtendedTrait {
+use std::process::Command;
+use std::sync::{Arc, LazyLock, RwLock};
+
+///Extracts searchable text from files that are not plain text.
+///Implement this and `register` it to search other file types.
+pub trait ExtendedTrait: Send + Sync {
+    ///unique name, also shown next to results
     fn name(&self) -> String;
     ///lowercase extensions
     fn extensions(&self) -> Vec<String>;
-    fn to_string(&self, path: &Path) -> Result<String, Box<dyn std::error::Error>>;
+    fn to_string(&self, path: &Path) -> Result<String, Box<dyn std::error::Error + Send + Sync>>;
+    ///For files containing other files, such as archives, the text of each entry as (entry path, text).
+    ///By default the whole file is a single entry with an empty entry path
+    fn to_entries(&self, path: &Path) -> Result<Vec<(String, String)>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(vec![(String::new(), self.to_string(path)?)])
+    }
+}
+
+///Separates the archive path from the entry path e.g. `bundle.zip!/src/main.rs`
+pub const ENTRY_SEPARATOR: &str = "!/";
+///Larger archive entries are only searched up to this size
+const MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+///Archives with more text or entries than this are not searched, so a large archive (or a zip bomb) is not read into memory
+const MAX_ARCHIVE_SIZE: u64 = 256 * 1024 * 1024;
+const MAX_ARCHIVE_ENTRIES: usize = 100_000;
+
+static REGISTRY: LazyLock<RwLock<Vec<Arc<dyn ExtendedTrait>>>> = LazyLock::new(|| {
+    RwLock::new(vec![
+        Arc::new(ExtendedType::Pdf),
+        Arc::new(ExtendedType::Office),
+        Arc::new(ExtendedType::Archive),
+    ])
+});
+
+///Adds an extractor used when searching with `extended`. Replaces any extractor with the same name
+pub fn register(extractor: impl ExtendedTrait + 'static) {
+    let mut registry = REGISTRY.write().unwrap();
+    let name = extractor.name();
+    registry.retain(|x| x.name() != name);
+    registry.push(Arc::new(extractor));
+}
+
+///Removes the extractor with this name, including the built in ones
+pub fn unregister(name: &str) {
+    REGISTRY.write().unwrap().retain(|x| x.name() != name);
+}
+
+///All registered extractors
+pub fn extractors() -> Vec<Arc<dyn ExtendedTrait>> {
+    REGISTRY.read().unwrap().clone()
+}
+
+///Registered extractors that handle this (lowercase) extension
+pub fn extractors_for(extension: &str) -> Vec<Arc<dyn ExtendedTrait>> {
+    REGISTRY
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|x| x.extensions().iter().any(|e| e == extension))
+        .cloned()
+        .collect()
+}
+
+///Extractor that runs an external program and searches what it prints to stdout.
+///These can be added to `ContentOptions::extractors` in config.toml e.g.
+///```toml
+///[[content.extractors]]
+///name = "Notebook"
+///extensions = ["ipynb"]
+///command = "jupyter nbconvert --to script --stdout {path}"
+///```
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CommandExtractor {
+    pub name: String,
+    pub extensions: Vec<String>,
+    ///program and arguments separated by spaces, `{path}` is replaced with the file path
+    pub command: String,
+}
+
+impl ExtendedTrait for CommandExtractor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        self.extensions.iter().map(|x| x.to_lowercase()).collect()
+    }
+
+    fn to_string(&self, path: &Path) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let path = path.to_string_lossy();
+        let mut args = self.command.split_whitespace().map(|x| x.replace("{path}", &path));
+        let program = args.next().ok_or("empty command")?;
+        let output = Command::new(program).args(args).output()?;
+        if !output.status.success() {
+            return Err(format!("{} failed: {}", self.name, String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
 }
 #[derive(Clone, Copy, Serialize, Deserialize, Debug)]
 pub enum ExtendedType {
     Pdf,
     Office,
+    Archive,
 }
 
 impl ExtendedTrait for ExtendedType {
@@ -29,13 +125,31 @@ impl ExtendedTrait for ExtendedType {
                 "odt".to_string(),
                 "odp".to_string(),
             ],
+            ExtendedType::Archive => vec![
+                "zip".to_string(),
+                "tar".to_string(),
+                "gz".to_string(),
+                "tgz".to_string(),
+                "xz".to_string(),
+                "txz".to_string(),
+                "bz2".to_string(),
+                "tbz2".to_string(),
+            ],
         }
     }
 
-    fn to_string(&self, path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    fn to_string(&self, path: &Path) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         match self {
             ExtendedType::Pdf => Ok(extract_pdf(path)?),
             ExtendedType::Office => Ok(extract_office(path)?),
+            ExtendedType::Archive => Ok(extract_archive(path)?.into_iter().map(|(_, text)| text).collect::<Vec<_>>().join("\n")),
+        }
+    }
+
+    fn to_entries(&self, path: &Path) -> Result<Vec<(String, String)>, Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            ExtendedType::Archive => extract_archive(path),
+            _ => Ok(vec![(String::new(), self.to_string(path)?)]),
         }
     }
 
@@ -43,28 +157,31 @@ impl ExtendedTrait for ExtendedType {
         match self {
             ExtendedType::Pdf => "Pdf",
             ExtendedType::Office => "Office",
+            ExtendedType::Archive => "Archive",
         }
         .to_string()
     }
 }
-impl From<&str> for ExtendedType {
-    fn from(value: &str) -> Self {
+impl TryFrom<&str> for ExtendedType {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value.to_lowercase().as_str() {
-            "pdf" => ExtendedType::Pdf,
-            "office" => ExtendedType::Office,
-            _ => panic!("unknown extended type"),
+            "pdf" => Ok(ExtendedType::Pdf),
+            "office" => Ok(ExtendedType::Office),
+            "archive" => Ok(ExtendedType::Archive),
+            _ => Err(format!("unknown extended type {value}")),
         }
     }
 }
 
-fn extract_pdf(path: &Path) -> Result<String, Box<dyn Error>> {
+fn extract_pdf(path: &Path) -> Result<String, Box<dyn Error + Send + Sync>> {
     let path = path.to_owned();
     //because the library panics, we need to catch panics
     let res = catch_unwind(|| pdf_extract::extract_text(&path));
     Ok(res.map_err(|_| "Panicked".to_string())??)
 }
 
-fn extract_office(path: &Path) -> Result<String, Box<dyn Error>> {
+fn extract_office(path: &Path) -> Result<String, Box<dyn Error + Send + Sync>> {
     let ext = path.extension().unwrap_or_default().to_string_lossy().to_string();
     let string = match ext.as_str() {
         "docx" => markdownify::docx::parse_docx(&std::fs::read(path)?, true)?,
@@ -75,3 +192,128 @@ fn extract_office(path: &Path) -> Result<String, Box<dyn Error>> {
     };
     Ok(string)
 }
+
+///Each file in a zip or tar archive. Compressed gz, xz and bz2 files which are not tars are a single entry with an empty entry path
+fn extract_archive(path: &Path) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    let ext = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+    let file = File::open(path)?;
+    let mut entries = Entries::new(MAX_ARCHIVE_SIZE, MAX_ARCHIVE_ENTRIES);
+    match ext.as_str() {
+        "zip" => extract_zip(file, &mut entries)?,
+        "tar" => extract_tar(file, &mut entries)?,
+        "tgz" | "txz" | "tbz2" => extract_tar(decompress(&ext, file), &mut entries)?,
+        "gz" | "xz" | "bz2" if stem.ends_with(".tar") => extract_tar(decompress(&ext, file), &mut entries)?,
+        "gz" | "xz" | "bz2" => entries.push(String::new(), decompress(&ext, file))?,
+        _ => return Err("unknown extension".into()),
+    }
+    Ok(entries.entries)
+}
+
+fn decompress(ext: &str, file: File) -> Box<dyn Read> {
+    match ext {
+        "xz" | "txz" => Box::new(lzma_rust2::XzReader::new(file, true)),
+        "bz2" | "tbz2" => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+        _ => Box::new(flate2::read::MultiGzDecoder::new(file)),
+    }
+}
+
+fn extract_zip(file: File, entries: &mut Entries) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut zip = zip::ZipArchive::new(file)?;
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        entries.push(name, entry)?;
+    }
+    Ok(())
+}
+
+fn extract_tar(reader: impl Read, entries: &mut Entries) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().to_string();
+        entries.push(name, entry)?;
+    }
+    Ok(())
+}
+
+///Text of the entries of an archive, read until the archive is over its limits
+struct Entries {
+    entries: Vec<(String, String)>,
+    size: u64, //total bytes read
+    max_size: u64,
+    max_entries: usize,
+}
+
+impl Entries {
+    fn new(max_size: u64, max_entries: usize) -> Self {
+        Self {
+            entries: vec![],
+            size: 0,
+            max_size,
+            max_entries,
+        }
+    }
+
+    ///reads an entry up to `MAX_ENTRY_SIZE`, or fails if the archive is over its limits
+    fn push(&mut self, name: String, reader: impl Read) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.entries.len() >= self.max_entries {
+            return Err(format!("more than {} entries", self.max_entries).into());
+        }
+        //one byte more than is left, to know if it is over
+        let limit = MAX_ENTRY_SIZE.min(self.max_size - self.size + 1);
+        let mut data = vec![];
+        reader.take(limit).read_to_end(&mut data)?;
+        self.size += data.len() as u64;
+        if self.size > self.max_size {
+            return Err(format!("more than {} MB of text", self.max_size / 1024 / 1024).into());
+        }
+        self.entries.push((name, String::from_utf8_lossy(&data).to_string()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn command_extractor() {
+        register(CommandExtractor {
+            name: "Echo".to_string(),
+            extensions: vec!["ECHO".to_string()],
+            command: "echo hello {path}".to_string(),
+        });
+        let found = extractors_for("echo");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].to_string(Path::new("a.echo")).unwrap(), "hello a.echo\n");
+        unregister("Echo");
+        assert!(extractors_for("echo").is_empty());
+    }
+
+    #[test]
+    fn archive_limits() {
+        let mut builder = tar::Builder::new(vec![]);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(4);
+            header.set_cksum();
+            builder.append_data(&mut header, name, "text".as_bytes()).unwrap();
+        }
+        let tar = builder.into_inner().unwrap();
+
+        let mut entries = Entries::new(100, 10);
+        extract_tar(tar.as_slice(), &mut entries).unwrap();
+        assert_eq!(entries.entries.len(), 3);
+        assert!(extract_tar(tar.as_slice(), &mut Entries::new(100, 2)).is_err());
+        assert!(extract_tar(tar.as_slice(), &mut Entries::new(10, 10)).is_err());
+    }
+}
@@ -1,41 +1,106 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, fs, path::Path, time::SystemTime};
 
-use crate::extended::ExtendedType;
+use serde::Serialize;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct FileInfo {
     pub path: String,
+    pub root: String, //searched dir the file was found under
     pub matches: Vec<Match>,
     pub ext: String,
     pub name: String,
     pub is_folder: bool,
-    pub plugin: Option<ExtendedType>,
+    pub plugin: Option<String>,              //name of the extractor the matches were found with
     pub ranges: Vec<std::ops::Range<usize>>, //TODO: save ranges for highlighting
+    pub score: Option<i64>,                  //of fuzzy name matches, higher is better
+    pub size: Option<u64>,                   //in bytes, None for folders
+    pub modified: Option<SystemTime>,
 }
 
 impl FileInfo {
     pub fn content(&self, max_count: usize, max_length: usize) -> String {
-        self.matches
-            .iter()
+        self.unique_lines()
+            .into_iter()
             .take(max_count)
             .map(|x| FileInfo::limited_match(x, max_length, true))
             .collect::<Vec<String>>()
             .join("\n")
     }
+    ///size (of files) and modified time of a path, to sort by. None if they could not be read
+    pub fn metadata(path: &Path) -> (Option<u64>, Option<SystemTime>) {
+        match fs::metadata(path) {
+            Ok(meta) => (meta.is_file().then_some(meta.len()), meta.modified().ok()),
+            Err(_) => (None, None),
+        }
+    }
+    ///number of dirs and the file below the root, 1 for a file directly in it
+    pub fn depth(&self) -> usize {
+        let path = Path::new(&self.path);
+        path.strip_prefix(&self.root).unwrap_or(path).components().count()
+    }
+    ///number of matching lines, excluding context lines
+    pub fn match_count(&self) -> usize {
+        self.unique_lines().iter().filter(|x| !x.is_context).count()
+    }
+    ///matches with each line only once, where several content terms matched (or gave context to) the same line
+    pub fn unique_lines(&self) -> Vec<&Match> {
+        let mut lines: Vec<&Match> = vec![];
+        for mat in self.matches.iter() {
+            match lines.last_mut() {
+                Some(last) if last.line == mat.line => {
+                    if last.is_context && !mat.is_context {
+                        *last = mat;
+                    }
+                }
+                _ => lines.push(mat),
+            }
+        }
+        lines
+    }
     pub fn limited_match(x: &Match, max_length: usize, line_number: bool) -> String {
         //limit content line length
         let fixed = match x.content.char_indices().nth(max_length) {
             None => Cow::from(&x.content),
             Some((idx, _)) => Cow::from(format!("{}...", &x.content[..idx])),
         };
-        let num = if line_number { format!("{}: ", x.line) } else { String::new() };
+        //context lines are marked with - instead of :, like grep
+        let num = match (line_number, x.is_context) {
+            (true, false) => format!("{}: ", x.line),
+            (true, true) => format!("{}- ", x.line),
+            (false, _) => String::new(),
+        };
         format!("{}{}", num, fixed.trim_end())
     }
 }
 
-#[derive(Clone, Debug)]
+#[cfg(test)]
+impl FileInfo {
+    ///a file under root without matches, for tests to set the fields they need on
+    pub(crate) fn fixture(path: &str, root: &str) -> Self {
+        let file = Path::new(path);
+        Self {
+            path: path.to_string(),
+            root: root.to_string(),
+            matches: vec![],
+            ext: file.extension().unwrap_or_default().to_string_lossy().to_string(),
+            name: file.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            is_folder: false,
+            plugin: None,
+            ranges: vec![],
+            score: None,
+            size: None,
+            modified: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct Match {
     pub line: usize,
+    pub end_line: usize, //last line of the match, only differs from line for multiline matches
+    pub offset: u64,     //absolute byte offset of the line in the file
     pub content: String,
     pub ranges: Vec<std::ops::Range<usize>>,
+    pub is_context: bool, //line surrounding a match, rather than a match itself
+    pub term: usize,      //index of the content term the match is of, see `Search::content_terms`
 }
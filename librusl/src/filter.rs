@@ -0,0 +1,248 @@
+use std::{
+    fs::Metadata,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+
+use crate::options::NameOptions;
+
+///Filters on the size, modified and created time of files, parsed from `NameOptions`.
+///
+///Each filter is a value, a comparison (`>`, `>=`, `<`, `<=`) with a value, or a range `a..b` (inclusive).
+///- sizes are a number with an optional unit, e.g. `>10MB`, `<=512k`, `1MiB..2GiB`. A bare size matches that size exactly
+///- times are a date `2024-01-31`, a date and time `2024-01-31 14:00`, `today`, `yesterday`, or an age such as
+///  `7d`, `3 hours`, `last 2 weeks`. Comparisons of dates are on time, so `<2024-01-31` is before that day, and of ages
+///  on age, so `<7d` is less than 7 days old and `>7d` older. A bare date matches that day, and a bare age matches anything since then
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataFilter {
+    size: Option<Span<u64>>,
+    modified: Option<Span<SystemTime>>,
+    created: Option<Span<SystemTime>>,
+}
+
+///from start (inclusive) to end (exclusive), None is unbounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span<T> {
+    start: Option<T>,
+    end: Option<T>,
+}
+
+impl<T: PartialOrd> Span<T> {
+    fn contains(&self, value: &T) -> bool {
+        self.start.as_ref().is_none_or(|s| value >= s) && self.end.as_ref().is_none_or(|e| value < e)
+    }
+}
+
+impl MetadataFilter {
+    pub fn new(ops: &NameOptions) -> Result<Self, String> {
+        let now = SystemTime::now();
+        Ok(Self {
+            size: parse_span(&ops.size, parse_size).map_err(|e| format!("Invalid size filter '{}': {e}", ops.size))?,
+            modified: parse_time_filter(&ops.modified, now).map_err(|e| format!("Invalid modified filter '{}': {e}", ops.modified))?,
+            created: parse_time_filter(&ops.created, now).map_err(|e| format!("Invalid created filter '{}': {e}", ops.created))?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size.is_none() && self.modified.is_none() && self.created.is_none()
+    }
+
+    ///size filters only match files. If a time is not available on this platform, the file does not match
+    pub fn matches(&self, meta: &Metadata) -> bool {
+        if let Some(size) = &self.size
+            && (meta.is_dir() || !size.contains(&meta.len()))
+        {
+            return false;
+        }
+        if let Some(modified) = &self.modified
+            && !meta.modified().is_ok_and(|t| modified.contains(&t))
+        {
+            return false;
+        }
+        if let Some(created) = &self.created
+            && !meta.created().is_ok_and(|t| created.contains(&t))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+fn parse_span<T: Copy>(text: &str, value: impl Fn(&str) -> Result<Span<T>, String>) -> Result<Option<Span<T>>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let span = if let Some((from, to)) = text.split_once("..") {
+        Span {
+            start: value(from.trim())?.start,
+            end: value(to.trim())?.end,
+        }
+    } else if let Some(v) = text.strip_prefix(">=") {
+        Span {
+            start: value(v.trim())?.start,
+            end: None,
+        }
+    } else if let Some(v) = text.strip_prefix('>') {
+        Span {
+            start: value(v.trim())?.end,
+            end: None,
+        }
+    } else if let Some(v) = text.strip_prefix("<=") {
+        Span {
+            start: None,
+            end: value(v.trim())?.end,
+        }
+    } else if let Some(v) = text.strip_prefix('<') {
+        Span {
+            start: None,
+            end: value(v.trim())?.start,
+        }
+    } else {
+        value(text)?
+    };
+    Ok(Some(span))
+}
+
+fn parse_size(text: &str) -> Result<Span<u64>, String> {
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("'{number}' is not a number"))?;
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "ki" | "kib" => 1 << 10,
+        "m" | "mb" => 1000 * 1000,
+        "mi" | "mib" => 1 << 20,
+        "g" | "gb" => 1000 * 1000 * 1000,
+        "gi" | "gib" => 1 << 30,
+        "t" | "tb" => 1000 * 1000 * 1000 * 1000,
+        "ti" | "tib" => 1 << 40,
+        unit => return Err(format!("unknown unit '{unit}', use b, kb, mb, gb, tb or kib, mib, gib, tib")),
+    };
+    let size = (number * multiplier as f64) as u64;
+    Ok(Span {
+        start: Some(size),
+        end: Some(size + 1),
+    })
+}
+
+fn parse_time_filter(text: &str, now: SystemTime) -> Result<Option<Span<SystemTime>>, String> {
+    let text = flip_ages(text.trim());
+    //bare ages mean since then
+    let text = match text.strip_prefix("last ").or_else(|| text.strip_prefix("within ")) {
+        Some(age) => format!(">={age}"),
+        None if parse_age(&text).is_ok() => format!(">={text}"),
+        None => text,
+    };
+    parse_span(&text, |v| parse_time(v, now))
+}
+
+///comparisons and ranges of ages are on the age, which is the opposite of the time, e.g. `<7d` is `>` 7 days ago
+fn flip_ages(text: &str) -> String {
+    if let Some((from, to)) = text.split_once("..")
+        && parse_age(from.trim()).is_ok()
+        && parse_age(to.trim()).is_ok()
+    {
+        return format!("{}..{}", to.trim(), from.trim());
+    }
+    for (op, flipped) in [(">=", "<="), ("<=", ">="), (">", "<"), ("<", ">")] {
+        if let Some(age) = text.strip_prefix(op)
+            && parse_age(age.trim()).is_ok()
+        {
+            return format!("{flipped}{age}");
+        }
+    }
+    text.to_string()
+}
+
+fn parse_time(text: &str, now: SystemTime) -> Result<Span<SystemTime>, String> {
+    let today = Local::now().date_naive();
+    let date = match text.to_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        _ => NaiveDate::parse_from_str(text, "%Y-%m-%d").ok(),
+    };
+    if let Some(date) = date {
+        let start = local_time(date.and_hms_opt(0, 0, 0).unwrap_or_default())?;
+        let end = local_time(date.succ_opt().unwrap_or(date).and_hms_opt(0, 0, 0).unwrap_or_default())?;
+        return Ok(Span {
+            start: Some(start),
+            end: Some(end),
+        });
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(text, format) {
+            let start = local_time(time)?;
+            return Ok(Span {
+                start: Some(start),
+                end: Some(start + Duration::from_secs(1)),
+            });
+        }
+    }
+    let age = parse_age(text).map_err(|e| format!("{e}, or a date like 2024-01-31"))?;
+    let time = now.checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH);
+    Ok(Span {
+        start: Some(time),
+        end: Some(time),
+    })
+}
+
+fn local_time(time: NaiveDateTime) -> Result<SystemTime, String> {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("{time} does not exist in the local time zone"))
+}
+
+///e.g. `7d`, `3 hours`, `1.5w`
+fn parse_age(text: &str) -> Result<Duration, String> {
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("'{text}' is not an age like 7d"))?;
+    let seconds = match unit.trim().to_lowercase().as_str() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        "w" | "week" | "weeks" => 7 * 24 * 60 * 60,
+        "mon" | "month" | "months" => 30 * 24 * 60 * 60,
+        "y" | "year" | "years" => 365 * 24 * 60 * 60,
+        unit => return Err(format!("unknown unit '{unit}', use s, min, h, d, w, mon or y")),
+    };
+    Ok(Duration::from_secs_f64(number * seconds as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let size = |text: &str| parse_span(text, parse_size).unwrap().unwrap();
+        assert!(size(">10MB").contains(&(10_000_001)));
+        assert!(!size(">10MB").contains(&(10_000_000)));
+        assert!(size("<=1KiB").contains(&1024));
+        assert!(size("1k..2k").contains(&2000));
+        assert!(!size("1k..2k").contains(&2001));
+        assert!(parse_span("10 parsecs", parse_size).is_err());
+
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let time = |text: &str| parse_time_filter(text, now).unwrap().unwrap();
+        assert!(time("7d").contains(&(now - day)));
+        assert!(!time("last 7 days").contains(&(now - 8 * day)));
+        assert!(time("<7d").contains(&(now - day)));
+        assert!(!time("<7d").contains(&(now - 8 * day)));
+        assert!(time(">7d").contains(&(now - 8 * day)));
+        assert!(!time(">7d").contains(&(now - day)));
+        assert!(time("1d..7d").contains(&(now - 2 * day)));
+        assert!(!time("1d..7d").contains(&(now - 8 * day)));
+        assert!(time("2024-01-31").contains(&local_time(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap().and_hms_opt(23, 0, 0).unwrap()).unwrap()));
+        assert!(!time("<2024-01-31").contains(&local_time(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap().and_hms_opt(1, 0, 0).unwrap()).unwrap()));
+        assert!(parse_time_filter("", now).unwrap().is_none());
+        assert!(parse_time_filter("next tuesday", now).is_err());
+    }
+}
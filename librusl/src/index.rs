@@ -0,0 +1,410 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use regex_syntax::hir::{Hir, HirKind};
+use serde::{Deserialize, Serialize};
+
+use crate::options::ContentOptions;
+use crate::search::Search;
+
+///bumped when the format changes, so old indexes are rebuilt instead of misread
+const INDEX_VERSION: u32 = 2;
+///larger files are still searched every time
+pub const MAX_INDEXED_SIZE: u64 = 16 * 1024 * 1024;
+///extracted text of a file is only kept if it is smaller than this, otherwise only its trigrams are
+const MAX_CACHED_TEXT: usize = 1024 * 1024;
+
+///Index of the files under a directory (or several searched together), saved under the rusl config dir.
+///Each file is keyed by its path, modified time and size, and keeps the trigrams of its contents
+///(so files that cannot match a pattern are skipped), and of the text extracted from it by extended extractors,
+///along with that text if it is small. Entries of changed files are rebuilt as they are searched, and entries of files no longer on disk are pruned
+pub struct ContentIndex {
+    root: String,
+    file: Option<PathBuf>,
+    entries: RwLock<HashMap<String, IndexEntry>>,
+    seen: Mutex<Option<(usize, HashSet<String>)>>, //files found by the search of this id, since `start`
+    changed: AtomicBool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexData<'a> {
+    version: u32,
+    root: Cow<'a, str>,
+    entries: Cow<'a, HashMap<String, IndexEntry>>, //borrowed when saving, as it can be large
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IndexEntry {
+    modified: Duration, //since unix epoch
+    size: u64,
+    trigrams: Option<Trigrams>, //None if the file was too big to index
+    pub extracted: Option<Extracted>,
+}
+
+///What the extended extractors found in a file: the trigrams of its text, and the text as (extractor name, entry, text) if it is small
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Extracted {
+    pub extractors: Vec<String>, //extractors that were run, so adding one rebuilds the entry
+    trigrams: Trigrams,
+    pub entries: Option<Vec<(String, String, String)>>, //None if over `MAX_CACHED_TEXT`, then it is extracted again when it may match
+}
+
+impl ContentIndex {
+    ///loads the index of `root` from the config dir, or an empty one if there is none yet
+    pub fn load(root: &str) -> Self {
+        let root = root_key(root);
+        let file = index_file(&root);
+        let mut index = Self::new(&root, file.clone());
+        if let Some(data) = file.and_then(|f| fs::read(f).ok())
+            && let Ok(data) = postcard::from_bytes::<IndexData>(&data)
+            && data.version == INDEX_VERSION
+            && data.root == root
+        {
+            index.entries = RwLock::new(data.entries.into_owned());
+        }
+        index
+    }
+
+    ///an index kept in memory only, or saved to `file`
+    pub fn new(root: &str, file: Option<PathBuf>) -> Self {
+        Self {
+            root: root.to_string(),
+            file,
+            entries: Default::default(),
+            seen: Default::default(),
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    ///deletes the saved index of `root`
+    pub fn delete(root: &str) -> std::io::Result<()> {
+        match index_file(&root_key(root)) {
+            Some(file) if file.exists() => fs::remove_file(file),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_root(&self, dir: &str) -> bool {
+        self.root == root_key(dir)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///the entry of `path` if the file has not changed since it was indexed
+    pub fn get(&self, path: &Path, key: &FileKey) -> Option<IndexEntry> {
+        let path = path.to_string_lossy().to_string();
+        let entry = self
+            .entries
+            .read()
+            .unwrap()
+            .get(&path)
+            .filter(|e| e.modified == key.modified && e.size == key.size)
+            .cloned();
+        self.see(path);
+        entry
+    }
+
+    pub fn insert(&self, path: &Path, entry: IndexEntry) {
+        let path = path.to_string_lossy().to_string();
+        self.see(path.clone());
+        self.entries.write().unwrap().insert(path, entry);
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    fn see(&self, path: String) {
+        if let Some((_, seen)) = self.seen.lock().unwrap().as_mut() {
+            seen.insert(path);
+        }
+    }
+
+    ///start keeping the files seen by the search of this id, before it searches the whole root.
+    ///Files an older search still finds are kept too, they exist
+    pub fn start(&self, search_id: usize) {
+        *self.seen.lock().unwrap() = Some((search_id, HashSet::new()));
+    }
+
+    ///removes entries of files that no longer exist. Files seen since `start` of this search are not checked again,
+    ///others may just have been skipped by the filters of the search. Does nothing if another search started since
+    pub fn prune(&self, search_id: usize) {
+        let seen = {
+            let mut seen = self.seen.lock().unwrap();
+            match seen.take() {
+                Some((id, files)) if id == search_id => files,
+                other => {
+                    *seen = other;
+                    return;
+                }
+            }
+        };
+        let mut entries = self.entries.write().unwrap();
+        let count = entries.len();
+        entries.retain(|path, _| seen.contains(path) || Path::new(path).exists());
+        if entries.len() != count {
+            self.changed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    ///writes the index if it changed since it was loaded or last saved
+    pub fn save(&self) -> Result<(), String> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if !self.changed.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let entries = self.entries.read().unwrap();
+        let data = IndexData {
+            version: INDEX_VERSION,
+            root: Cow::from(&self.root),
+            entries: Cow::Borrowed(&entries),
+        };
+        let bytes = postcard::to_allocvec(&data).map_err(|e| format!("Could not save index: {e}"))?;
+        if let Some(parent) = file.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        //write to temp first, so a crash never leaves a half written index
+        let temp = file.with_extension("tmp");
+        fs::write(&temp, bytes)
+            .and_then(|_| fs::rename(&temp, file))
+            .map_err(|e| format!("Could not save index {}: {e}", file.display()))
+    }
+}
+
+impl Extracted {
+    pub fn new(extractors: Vec<String>, entries: &[(String, String, String)]) -> Self {
+        let trigrams = Trigrams::of_texts(entries.iter().map(|(_, _, text)| text.as_str()));
+        let size: usize = entries.iter().map(|(_, _, text)| text.len()).sum();
+        Self {
+            extractors,
+            trigrams,
+            entries: (size <= MAX_CACHED_TEXT).then(|| entries.to_vec()),
+        }
+    }
+
+    ///false if no extracted text can contain a match of the query
+    pub fn may_match(&self, query: &IndexQuery) -> bool {
+        query.trigrams.iter().all(|t| self.trigrams.contains(*t))
+    }
+}
+
+impl IndexEntry {
+    ///new entry of a file, with the trigrams of its contents if it was small enough to read
+    pub fn new(key: &FileKey, contents: Option<&[u8]>) -> Self {
+        Self {
+            modified: key.modified,
+            size: key.size,
+            trigrams: contents.and_then(Trigrams::new),
+            extracted: None,
+        }
+    }
+
+    ///false if the file cannot contain a match of the query
+    pub fn may_match(&self, query: &IndexQuery) -> bool {
+        match &self.trigrams {
+            Some(trigrams) => query.trigrams.iter().all(|t| trigrams.contains(*t)),
+            None => true,
+        }
+    }
+}
+
+///what an entry is keyed on, besides its path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileKey {
+    modified: Duration,
+    size: u64,
+}
+
+impl FileKey {
+    pub fn new(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: meta.modified().ok()?.duration_since(SystemTime::UNIX_EPOCH).ok()?,
+            size: meta.len(),
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+///Trigrams that every match of a content pattern contains
+#[derive(Debug, Clone, Default)]
+pub struct IndexQuery {
+    trigrams: Vec<u32>,
+}
+
+impl IndexQuery {
+    pub fn new(pattern: &str, ops: &ContentOptions) -> Self {
+        let pattern = if ops.nonregex {
+            regex_syntax::escape(pattern)
+        } else {
+            pattern.to_string()
+        };
+        //parsed case sensitive, otherwise literals become classes like [aA]
+        let Ok(hir) = regex_syntax::Parser::new().parse(&pattern) else {
+            return Self::default();
+        };
+        let mut trigrams = vec![];
+        for literal in required_literals(&hir) {
+            for window in literal.windows(3) {
+                //the index folds ascii case only, and case insensitive k and s also match unicode (kelvin sign, long s)
+                if !ops.case_sensitive && window.iter().any(|b| !b.is_ascii() || matches!(b.to_ascii_lowercase(), b'k' | b's')) {
+                    continue;
+                }
+                trigrams.push(trigram(window));
+            }
+        }
+        trigrams.sort_unstable();
+        trigrams.dedup();
+        Self { trigrams }
+    }
+
+    ///true if the query can skip files
+    pub fn is_useful(&self) -> bool {
+        !self.trigrams.is_empty()
+    }
+}
+
+///literals that any match of the pattern must contain
+fn required_literals(hir: &Hir) -> Vec<Vec<u8>> {
+    match hir.kind() {
+        HirKind::Literal(lit) => vec![lit.0.to_vec()],
+        HirKind::Capture(cap) => required_literals(&cap.sub),
+        HirKind::Repetition(rep) if rep.min > 0 => required_literals(&rep.sub),
+        HirKind::Concat(subs) => subs.iter().flat_map(required_literals).collect(),
+        _ => vec![],
+    }
+}
+
+fn trigram(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |t, b| (t << 8) | b.to_ascii_lowercase() as u32)
+}
+
+///Bloom filter of the (ascii lowercased) trigrams of a file. Sized to the number of trigrams, so few files pass that should not
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Trigrams {
+    bits: Vec<u64>,
+}
+
+impl Trigrams {
+    fn new(contents: &[u8]) -> Option<Self> {
+        //the searcher decodes utf-16 files, so their bytes are not what is matched
+        if contents.starts_with(&[0xFF, 0xFE]) || contents.starts_with(&[0xFE, 0xFF]) {
+            return None;
+        }
+        //binary files are only searched up to the first NUL
+        let end = contents.iter().position(|b| *b == 0).unwrap_or(contents.len());
+        Some(Self::from_set(contents[..end].windows(3).map(trigram).collect()))
+    }
+
+    ///of several texts, e.g. the entries of an archive
+    fn of_texts<'a>(texts: impl Iterator<Item = &'a str>) -> Self {
+        Self::from_set(texts.flat_map(|text| text.as_bytes().windows(3).map(trigram)).collect())
+    }
+
+    fn from_set(set: HashSet<u32>) -> Self {
+        let words = (set.len() * 4 / 64).next_power_of_two().clamp(8, 1024);
+        let mut trigrams = Self { bits: vec![0; words] };
+        for t in set {
+            let bit = trigrams.bit(t);
+            trigrams.bits[bit / 64] |= 1 << (bit % 64);
+        }
+        trigrams
+    }
+
+    fn contains(&self, t: u32) -> bool {
+        let bit = self.bit(t);
+        self.bits[bit / 64] & (1 << (bit % 64)) != 0
+    }
+
+    fn bit(&self, t: u32) -> usize {
+        let shift = (self.bits.len() * 64).trailing_zeros();
+        (t.wrapping_mul(0x9E37_79B1) >> (32 - shift)) as usize
+    }
+}
+
+///dirs searched together are joined by `DIR_SEPARATOR`, and share an index
+fn root_key(dirs: &str) -> String {
+    let dirs: Vec<String> = Search::split_dirs(dirs)
+        .into_iter()
+        .map(|dir| fs::canonicalize(&dir).map(|p| p.to_string_lossy().to_string()).unwrap_or(dir))
+        .collect();
+    Search::join_dirs(&dirs)
+}
+
+///each root has its own file in the config dir, named by a hash of the root
+fn index_file(root: &str) -> Option<PathBuf> {
+    //fnv-1a, as it does not change between rust versions
+    let hash = root
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3));
+    let mut dir = dirs::config_dir()?;
+    dir.push("rusl");
+    dir.push("index");
+    dir.push(format!("{hash:016x}.idx"));
+    Some(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_skips_files() {
+        let key = FileKey {
+            modified: Duration::from_secs(1),
+            size: 10,
+        };
+        let entry = IndexEntry::new(&key, Some(b"fn main() {\n    println!(\"Hello\");\n}"));
+        let ops = ContentOptions::default();
+        assert!(entry.may_match(&IndexQuery::new("hello", &ops)));
+        assert!(entry.may_match(&IndexQuery::new(r"print\w+!\(", &ops)));
+        assert!(entry.may_match(&IndexQuery::new("goodbye|hello", &ops)));
+        assert!(!entry.may_match(&IndexQuery::new("goodbye", &ops)));
+        assert!(!entry.may_match(&IndexQuery::new(r"fn \w+\(\) -> i32", &ops)));
+        assert!(!IndexQuery::new("a.c", &ops).is_useful());
+
+        let index = ContentIndex::new("root", None);
+        index.insert(Path::new("a"), entry.clone());
+        index.insert(Path::new("b"), entry.clone());
+        //exists, but skipped by the filters of the searches below
+        index.insert(Path::new("Cargo.toml"), entry);
+        assert!(index.get(Path::new("a"), &key).is_some());
+        //changed since indexed
+        assert!(index.get(Path::new("b"), &FileKey { size: 11, ..key }).is_none());
+        index.start(1);
+        index.get(Path::new("a"), &key);
+        //a newer search started, so the older one does not prune
+        index.start(2);
+        index.prune(1);
+        assert_eq!(index.len(), 3);
+        index.get(Path::new("a"), &key);
+        index.prune(2);
+        assert_eq!(index.len(), 2);
+        assert!(index.get(Path::new("b"), &key).is_none());
+
+        let extracted = Extracted::new(
+            vec!["Archive".to_string()],
+            &[("Archive".to_string(), "a.txt".to_string(), "needle".to_string())],
+        );
+        assert!(extracted.entries.is_some());
+        assert!(extracted.may_match(&IndexQuery::new("needle", &ops)));
+        assert!(!extracted.may_match(&IndexQuery::new("haystack", &ops)));
+    }
+}
@@ -1,6 +1,14 @@
+pub mod error;
+pub mod export;
 pub mod extended;
 pub mod fileinfo;
+pub mod filter;
+pub mod index;
 pub mod manager;
+pub mod namematcher;
+pub mod open;
 pub mod options;
+pub mod replace;
 pub mod rgtools;
 pub mod search;
+pub mod store;
@@ -1,28 +1,36 @@
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::fmt;
+use std::fs::FileType;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use ignore::WalkBuilder;
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
 
-use crate::extended::ExtendedType;
-use crate::fileinfo::{FileInfo, Match};
-use crate::options::{FTypes, Options, Sort};
-use crate::rgtools::{self, EXTENSION_SEPARATOR, SEPARATOR};
-use crate::search::Search;
+use crate::error::{PatternError, PatternField, SearchError};
+use crate::extended;
+use crate::fileinfo::FileInfo;
+use crate::filter::MetadataFilter;
+use crate::index::ContentIndex;
+use crate::namematcher::NameMatcher;
+use crate::options::{FTypes, NameOptions, Options, SavedSearch, Sort};
+use crate::rgtools;
+use crate::search::{ContentTerm, Search};
+use crate::store::{ResultStore, SharedResults};
 
 pub enum Message {
     File(FileInfo, usize),
     Done(usize, Duration, bool), //id, elapsed, stopped
-    ContentFiles(Vec<FileInfo>, usize, Duration),
     StartSearch(usize),
-    FileErrors(Vec<String>),
-    SearchCount(usize), //number of files we went through
+    FileErrors(Vec<SearchError>),
+    SearchCount(usize),                   //number of files we went through
+    Watched(String, Vec<FileInfo>, bool), //path that changed, what it now matches, if it was removed
     Quit,
 }
 
@@ -30,22 +38,72 @@ pub enum Message {
 pub enum SearchResult {
     FinalResults(FinalResults),
     InterimResult(FileInfo),
-    SearchErrors(Vec<String>),
+    SearchErrors(Vec<SearchError>),
     SearchCount(usize),
+    Watch(WatchEvent), //a change to the final results while watching
 }
+
+///Change to the results of the last search, found while watching its directories
 #[derive(Debug, Clone)]
+pub enum WatchEvent {
+    Added(FileInfo),
+    Updated(FileInfo),
+    Removed(String), //path
+}
+
+impl WatchEvent {
+    ///applies the event to results, to keep them live
+    pub fn apply(&self, files: &mut Vec<FileInfo>) {
+        match self {
+            WatchEvent::Added(fi) => files.push(fi.clone()),
+            WatchEvent::Updated(fi) => {
+                if let Some(old) = files.iter_mut().find(|x| x.path == fi.path) {
+                    *old = fi.clone();
+                }
+            }
+            WatchEvent::Removed(path) => files.retain(|x| &x.path != path),
+        }
+    }
+}
+
+impl fmt::Display for WatchEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchEvent::Added(fi) => write!(f, "Added {}", fi.path),
+            WatchEvent::Updated(fi) => write!(f, "Updated {}", fi.path),
+            WatchEvent::Removed(path) => write!(f, "Removed {path}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct FinalResults {
     pub data: Vec<FileInfo>,
     pub duration: Duration,
     pub id: usize,
     pub stopped: bool,
+    pub truncated: bool, //`Options::max_results` files were found, so the search stopped before the others
+}
+
+impl FinalResults {
+    ///applies a watch event, to keep results live
+    pub fn apply(&mut self, event: &WatchEvent) {
+        event.apply(&mut self.data);
+    }
 }
+
 pub struct Manager {
     internal_sender: Sender<Message>,     //send internal messages
     current_search_id: Arc<AtomicUsize>,  //we keep track of searches, and stop old searches
     total_search_count: Arc<AtomicUsize>, //we keep track of total number of files searched
     stopped: Arc<AtomicBool>,             //if we stopped the search
     options: Arc<Mutex<Options>>,
+    index: Arc<Mutex<Option<Arc<ContentIndex>>>>, //index of the last searched dir, kept to avoid reloading it
+    last_search: Option<Search>,
+    watcher: Option<RecommendedWatcher>, //Some while watching, dropping it stops watching
+    watch_id: Arc<AtomicUsize>,          //incremented to stop old watches from sending
+    results: SharedResults,              //results of the last search, see `results`
+    shared_only: Arc<AtomicBool>,        //if true, `FinalResults::data` is left empty
 }
 
 // Manager has an internal receiver channel to receive internal messages.
@@ -55,19 +113,38 @@ impl Manager {
     pub fn new(external_sender: Sender<SearchResult>) -> Self {
         let ops = load_options();
         let ops = Arc::new(Mutex::new(ops));
+        let current_search_id = Arc::new(AtomicUsize::new(0));
 
         //internal channel that sends results inside
         let (s, r) = std::sync::mpsc::channel();
         let ops_for_receiver = ops.clone();
+        let search_id_for_receiver = current_search_id.clone();
+        let results = Arc::new(RwLock::new(ResultStore::default()));
+        let results_for_receiver = results.clone();
+        let shared_only = Arc::new(AtomicBool::new(false));
+        let shared_only_for_receiver = shared_only.clone();
         thread::spawn(move || {
-            message_receiver(r, external_sender, ops_for_receiver);
+            message_receiver(
+                r,
+                external_sender,
+                ops_for_receiver,
+                search_id_for_receiver,
+                results_for_receiver,
+                shared_only_for_receiver,
+            );
         });
         Self {
             internal_sender: s,
-            current_search_id: Arc::new(AtomicUsize::new(0)),
+            current_search_id,
             options: ops,
             total_search_count: Arc::new(AtomicUsize::new(0)),
             stopped: Arc::new(AtomicBool::new(false)),
+            index: Arc::new(Mutex::new(None)),
+            last_search: None,
+            watcher: None,
+            watch_id: Arc::new(AtomicUsize::new(0)),
+            results,
+            shared_only,
         }
     }
 
@@ -77,12 +154,14 @@ impl Manager {
         self.stopped.store(true, Ordering::Relaxed);
     }
 
-    pub fn search(&mut self, search: &Search) {
+    ///starts a search, unless one of its patterns is not a valid regex
+    pub fn search(&mut self, search: &Search) -> Result<(), PatternError> {
+        Manager::validate(search, &self.options.lock().unwrap())?;
         self.stop();
         self.stopped.store(false, Ordering::Relaxed);
 
         let mut ops = self.options.lock().unwrap();
-        ops.last_dir = search.dir.clone();
+        ops.last_dir = Search::join_dirs(&search.dirs);
         if !search.name_text.is_empty() && !ops.name_history.contains(&search.name_text) {
             ops.name_history.push(search.name_text.clone());
         }
@@ -91,7 +170,72 @@ impl Manager {
         }
         drop(ops);
 
-        self.spawn_search(&search);
+        self.spawn_search(search);
+        self.last_search = Some(search.clone());
+        //keep watching, but the new search
+        if self.is_watching()
+            && let Err(err) = self.watch()
+        {
+            let _ = self.internal_sender.send(Message::FileErrors(vec![SearchError::other(None, err)]));
+        }
+        Ok(())
+    }
+
+    ///checks the name, contents and term patterns, so an invalid regex is not mistaken for nothing found
+    pub fn validate(search: &Search, options: &Options) -> Result<(), PatternError> {
+        if !search.name_text.is_empty()
+            && let Err(err) = NameMatcher::new(&search.name_text, &options.name)
+        {
+            return Err(PatternError::new(PatternField::Name, &search.name_text, &err));
+        }
+        let contents = (!search.contents_text.is_empty()).then_some((PatternField::Content, &search.contents_text));
+        let terms = search.terms.iter().enumerate().map(|(i, x)| (PatternField::Term(i), &x.pattern));
+        for (field, pattern) in contents.into_iter().chain(terms.filter(|(_, x)| !x.is_empty())) {
+            if let Err(err) = rgtools::content_matcher(pattern, &options.content) {
+                return Err(PatternError::new(field, pattern, &err));
+            }
+        }
+        Ok(())
+    }
+
+    ///Keeps the results of the last search live. Files that change, are added or removed under its directories
+    ///are matched against the search again, and sent as `SearchResult::Watch`. Later searches are watched too, until `unwatch`
+    pub fn watch(&mut self) -> Result<(), String> {
+        let Some(search) = self.last_search.clone() else {
+            return Err("Nothing searched to watch".to_string());
+        };
+        let id = self.watch_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (s, r) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                let _ = s.send(event);
+            }
+        })
+        .map_err(|err| format!("Could not watch: {err}"))?;
+        for dir in search.roots() {
+            watcher
+                .watch(Path::new(&dir), RecursiveMode::Recursive)
+                .map_err(|err| format!("Could not watch {dir}: {err}"))?;
+        }
+        //replacing the old watcher closes its channel, which ends its thread
+        self.watcher = Some(watcher);
+
+        let options = self.options.lock().unwrap().clone();
+        let file_sender = self.internal_sender.clone();
+        let watch_id = self.watch_id.clone();
+        thread::spawn(move || {
+            Manager::watch_changes(r, &search, &options, file_sender, watch_id, id);
+        });
+        Ok(())
+    }
+
+    pub fn unwatch(&mut self) {
+        self.watch_id.fetch_add(1, Ordering::Relaxed);
+        self.watcher = None;
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.watcher.is_some()
     }
 
     pub fn save_and_quit(&self) {
@@ -103,10 +247,38 @@ impl Manager {
         save_settings(&self.options.lock().unwrap());
     }
 
+    ///adds the saved search to the options (replacing one with the same name) and saves the settings
+    pub fn save_search(&mut self, saved: SavedSearch) {
+        let mut ops = self.options.lock().unwrap();
+        ops.add_saved_search(saved);
+        save_settings(&ops);
+    }
+
+    ///removes the saved search and saves the settings
+    pub fn remove_saved_search(&mut self, name: &str) {
+        let mut ops = self.options.lock().unwrap();
+        if ops.remove_saved_search(name) {
+            save_settings(&ops);
+        }
+    }
+
+    ///uses the options of a saved search, and returns its search to run, None if there is none with the name
+    pub fn load_saved_search(&mut self, name: &str) -> Option<Search> {
+        let mut ops = self.options.lock().unwrap();
+        let saved = ops.saved_search(name)?.clone();
+        ops.load_saved_search(&saved);
+        Some(saved.search)
+    }
+
     pub fn dir_is_valid(&self, dir: &str) -> bool {
         PathBuf::from(dir).exists()
     }
 
+    ///true if there is at least one dir, and all of them exist
+    pub fn dirs_are_valid(&self, dirs: &[String]) -> bool {
+        !dirs.is_empty() && dirs.iter().all(|x| self.dir_is_valid(x))
+    }
+
     pub fn set_options(&mut self, ops: Options) {
         *self.options.lock().unwrap() = ops;
     }
@@ -119,6 +291,17 @@ impl Manager {
         self.options.lock().unwrap().sort = sort;
     }
 
+    ///results of the last search, filled as they are found and sorted when it is done. Read a page of them
+    ///instead of cloning them all, e.g. for a virtualised list
+    pub fn results(&self) -> SharedResults {
+        self.results.clone()
+    }
+
+    ///if true, `FinalResults::data` is left empty, to not copy large results, and they are read from `results`
+    pub fn set_shared_only(&mut self, shared_only: bool) {
+        self.shared_only.store(shared_only, Ordering::Relaxed);
+    }
+
     fn spawn_search(&self, search: &Search) {
         let search = search.to_owned();
         self.total_search_count.store(0, Ordering::Relaxed);
@@ -154,11 +337,14 @@ impl Manager {
         let file_sender1 = file_sender.clone();
         let options1 = self.options.lock().unwrap().clone();
         let total_search_count1 = self.total_search_count.clone();
+        let index1 = self.index.clone();
 
         if !search.name_text.is_empty() {
             let counter_search_id = current_search_id1.clone();
             thread::spawn(move || {
                 let start = Instant::now();
+                let index = (options1.content.use_index && search1.has_contents())
+                    .then(|| Manager::load_index(&index1, &Search::join_dirs(&search1.roots())));
                 Manager::find_names(
                     &search1,
                     options1,
@@ -166,7 +352,12 @@ impl Manager {
                     current_search_id1,
                     start_search_id,
                     total_search_count1,
+                    index.clone(),
                 );
+                //only the matching names were searched, so nothing can be pruned
+                if let Some(Err(err)) = index.map(|index| index.save()) {
+                    let _ = file_sender1.send(Message::FileErrors(vec![SearchError::other(None, err)]));
+                }
                 let stopped = stopped.load(Ordering::Relaxed);
                 if let Err(err) = file_sender1.send(Message::Done(start_search_id, start.elapsed(), stopped)) {
                     eprintln!("Manager: Could not send result {start_search_id} {err:?}:{err}");
@@ -175,29 +366,51 @@ impl Manager {
             });
         }
         //do content search (only if name is empty, otherwise it will be spawned after)
-        else if !search.contents_text.is_empty() && search.name_text.is_empty() {
+        else if search.has_contents() && search.name_text.is_empty() {
             let current_search_id2 = self.current_search_id.clone();
             let options2 = self.options.lock().unwrap().clone();
             let total_search_count2 = self.total_search_count.clone();
+            let index2 = self.index.clone();
 
             thread::spawn(move || {
                 let start = Instant::now();
+                let roots = search.roots();
+                let index = options2
+                    .content
+                    .use_index
+                    .then(|| Manager::load_index(&index2, &Search::join_dirs(&roots)));
+                if let Some(index) = &index {
+                    index.start(start_search_id);
+                }
+                //send each file as soon as it is searched
+                let file_found = |file| {
+                    let _ = file_sender.send(Message::File(file, start_search_id));
+                };
                 let files = Manager::find_contents(
-                    &search.contents_text,
-                    &search.dir,
+                    &search.content_terms(),
+                    &roots,
                     &HashSet::new(),
                     options2,
-                    current_search_id2,
+                    current_search_id2.clone(),
                     start_search_id,
                     Some(total_search_count2),
+                    Some(&file_found),
+                    index.as_deref(),
                 );
+                let mut errors = files.errors;
+                if let Some(index) = index {
+                    //drop files removed since they were indexed
+                    if current_search_id2.load(Ordering::Relaxed) == start_search_id {
+                        index.prune(start_search_id);
+                    }
+                    if let Err(err) = index.save() {
+                        errors.push(SearchError::other(None, err));
+                    }
+                }
                 let stopped = stopped.load(Ordering::Relaxed);
-                file_sender
-                    .send(Message::ContentFiles(files.results, start_search_id, start.elapsed()))
-                    .unwrap();
-                file_sender.send(Message::FileErrors(files.errors)).unwrap();
-                file_sender.send(Message::Done(start_search_id, start.elapsed(), stopped)).unwrap();
-                eprintln!("Done content search");
+                //the receiver is gone if the manager was dropped
+                let _ = file_sender.send(Message::FileErrors(errors));
+                let _ = file_sender.send(Message::Done(start_search_id, start.elapsed(), stopped));
             });
         }
     }
@@ -209,222 +422,421 @@ impl Manager {
         global_search_id: Arc<AtomicUsize>, // current global id
         start_search_id: usize,             //id when starting this search
         total_search_count: Arc<AtomicUsize>,
+        index: Option<Arc<ContentIndex>>,
     ) {
-        let text = &search.name_text;
-        let dir = &search.dir;
-        let ftype = options.name.file_types;
-        let sens = options.name.case_sensitive;
-        let re = regex::RegexBuilder::new(text).case_insensitive(!sens).build();
-        if re.is_err() {
-            return;
-        }
-        let re = re.unwrap();
-        let re = Arc::new(re);
-
-        let walker = WalkBuilder::new(dir)
-            .follow_links(options.name.follow_links)
-            .same_file_system(options.name.same_filesystem)
-            .threads(num_cpus::get())
-            .hidden(options.name.ignore_dot)
-            .git_ignore(options.name.use_gitignore)
-            .build_parallel();
-
-        //walk dir
-        walker.run(|| {
-            let file_sender = file_sender.clone();
-            let re = re.clone();
-            let global_search_id = global_search_id.clone();
-            let options = options.clone();
-            let total_search_count = total_search_count.clone();
-            Box::new(move |result| {
-                if global_search_id.load(Ordering::Relaxed) != start_search_id {
-                    return ignore::WalkState::Quit;
-                }
-                //dont include root directory name itself
-                if let Ok(dent) = &result {
-                    if dent.depth() == 0 {
-                        return ignore::WalkState::Continue;
-                    }
-                }
-
-                total_search_count.fetch_add(1, Ordering::Relaxed);
-                let dent = match result {
-                    Ok(dent) => dent,
-                    Err(err) => {
-                        let _ = file_sender.send(Message::FileErrors(vec![err.to_string()]));
-                        return ignore::WalkState::Continue;
-                    }
-                };
+        let matcher = match NameMatcher::new(&search.name_text, &options.name) {
+            Ok(matcher) => Arc::new(matcher),
+            Err(err) => {
+                let _ = file_sender.send(Message::FileErrors(vec![SearchError::InvalidRegex {
+                    pattern: search.name_text.clone(),
+                    source: Arc::new(err),
+                }]));
+                return;
+            }
+        };
+        let filter = match MetadataFilter::new(&options.name) {
+            Ok(filter) => Arc::new(filter),
+            Err(message) => {
+                let _ = file_sender.send(Message::FileErrors(vec![SearchError::InvalidOptions { message }]));
+                return;
+            }
+        };
 
-                let fs_type = dent.file_type();
-                if fs_type.is_none() {
-                    return ignore::WalkState::Continue;
+        //each root is walked on its own, so that globs are relative to it
+        for root in search.roots() {
+            let walker = match rgtools::walk_builder(&[OsString::from(&root)], &options.name) {
+                Ok(builder) => builder.build_parallel(),
+                Err(message) => {
+                    let _ = file_sender.send(Message::FileErrors(vec![SearchError::InvalidOptions { message }]));
+                    return;
                 }
-                let fs_type = fs_type.unwrap();
+            };
 
-                //skip files if we dont want them
-                match ftype {
-                    FTypes::Files => {
-                        if !fs_type.is_file() {
+            //walk dir
+            walker.run(|| {
+                let file_sender = file_sender.clone();
+                let matcher = matcher.clone();
+                let global_search_id = global_search_id.clone();
+                let options = options.clone();
+                let total_search_count = total_search_count.clone();
+                let index = index.clone();
+                let filter = filter.clone();
+                let root = &root;
+                Box::new(move |result| {
+                    if global_search_id.load(Ordering::Relaxed) != start_search_id {
+                        return ignore::WalkState::Quit;
+                    }
+                    //dont include root directory name itself
+                    #[allow(clippy::collapsible_if)]
+                    if let Ok(dent) = &result {
+                        if dent.depth() == 0 {
                             return ignore::WalkState::Continue;
                         }
                     }
-                    FTypes::Directories => {
-                        if !fs_type.is_dir() {
+
+                    total_search_count.fetch_add(1, Ordering::Relaxed);
+                    let dent = match result {
+                        Ok(dent) => dent,
+                        Err(err) => {
+                            let _ = file_sender.send(Message::FileErrors(vec![SearchError::walk(err)]));
                             return ignore::WalkState::Continue;
                         }
+                    };
+
+                    let Some(fs_type) = dent.file_type() else {
+                        return ignore::WalkState::Continue;
+                    };
+                    if !filter.is_empty() && !dent.metadata().is_ok_and(|m| filter.matches(&m)) {
+                        return ignore::WalkState::Continue;
                     }
-                    _ => (),
-                }
-                let is_match = re.clone().is_match(dent.file_name().to_str().unwrap_or_default());
-
-                if is_match {
-                    let mut must_add = true;
-                    let mut matches = vec![];
-                    if !search.contents_text.is_empty() {
-                        if fs_type.is_dir() {
-                            must_add = false;
-                        } else {
-                            //check if contents match
-                            let cont = Manager::find_contents(
-                                &search.contents_text,
-                                dir,
-                                &HashSet::from_iter([dent.path().to_string_lossy().to_string()]),
-                                options.clone(),
-                                global_search_id.clone(),
-                                start_search_id,
-                                None,
-                            );
-                            if cont.results.is_empty() {
-                                must_add = false;
-                            } else {
-                                matches = cont.results[0].matches.clone();
-                            }
-
-                            if !cont.errors.is_empty() {
-                                file_sender.send(Message::FileErrors(cont.errors)).unwrap();
-                            }
-                        }
+                    let found = Manager::match_name(
+                        search,
+                        &matcher,
+                        root,
+                        dent.path(),
+                        fs_type,
+                        &options,
+                        global_search_id.clone(),
+                        start_search_id,
+                        index.as_deref(),
+                    );
+                    if !found.errors.is_empty() {
+                        let _ = file_sender.send(Message::FileErrors(found.errors));
                     }
+                    //a new search started
                     if global_search_id.load(Ordering::Relaxed) != start_search_id {
-                        eprintln!("New search started, stopping current search");
                         return ignore::WalkState::Quit;
                     }
-                    if must_add {
-                        //find all matches on name
-                        let regex_matches = re
-                            .find_iter(dent.file_name().to_str().unwrap_or_default())
-                            .map(|a| a.range())
-                            .collect::<Vec<_>>();
-
-                        let res = file_sender.send(Message::File(
-                            FileInfo {
-                                path: dent.path().to_string_lossy().to_string(),
-                                name: dent.file_name().to_string_lossy().to_string(),
-                                ext: PathBuf::from(dent.path())
-                                    .extension()
-                                    .unwrap_or(&OsString::from(""))
-                                    .to_str()
-                                    .unwrap_or_default()
-                                    .into(),
-                                matches,
-                                is_folder: dent.file_type().unwrap().is_dir(),
-                                plugin: None,
-                                ranges: regex_matches,
-                            },
-                            start_search_id,
-                        ));
+                    for fi in found.results {
                         //receiver closed, so we quit
-                        if res.is_err() {
+                        if file_sender.send(Message::File(fi, start_search_id)).is_err() {
                             eprintln!("receiver closed, stopping search");
                             return ignore::WalkState::Quit;
                         }
                     }
+
+                    ignore::WalkState::Continue
+                })
+            });
+        }
+    }
+
+    ///Matches a path against the name search, and its contents against the content search if there is one.
+    ///Returns the file if both match
+    #[allow(clippy::too_many_arguments)]
+    fn match_name(
+        search: &Search,
+        matcher: &NameMatcher,
+        root: &str,
+        path: &Path,
+        fs_type: FileType,
+        options: &Options,
+        global_search_id: Arc<AtomicUsize>,
+        start_search_id: usize,
+        index: Option<&ContentIndex>,
+    ) -> ContentFileInfoResults {
+        let mut found = ContentFileInfoResults::default();
+        //skip files if we dont want them
+        match options.name.file_types {
+            FTypes::Files if !fs_type.is_file() => return found,
+            FTypes::Directories if !fs_type.is_dir() => return found,
+            _ => (),
+        }
+        let name = path.file_name().unwrap_or_default();
+        let Some(name_match) = matcher.find(name.to_str().unwrap_or_default()) else {
+            return found;
+        };
+
+        let mut matches = vec![];
+        let mut plugin = None;
+        let mut entries = vec![];
+        if search.has_contents() {
+            if fs_type.is_dir() {
+                return found;
+            }
+            //check if contents match
+            let cont = Manager::find_contents(
+                &search.content_terms(),
+                &[root.to_string()],
+                &HashSet::from_iter([path.to_string_lossy().to_string()]),
+                options.clone(),
+                global_search_id,
+                start_search_id,
+                None,
+                None,
+                index,
+            );
+            found.errors = cont.errors;
+            //matching entries of an archive are results of their own, besides the archive itself if it matched
+            let path_text = path.to_string_lossy();
+            let (file, others): (Vec<FileInfo>, Vec<FileInfo>) = cont.results.into_iter().partition(|x| x.path == path_text);
+            entries = others;
+            match file.into_iter().next() {
+                Some(fi) => {
+                    matches = fi.matches;
+                    plugin = fi.plugin;
                 }
+                None => {
+                    found.results = entries;
+                    return found;
+                }
+            }
+        }
 
-                ignore::WalkState::Continue
-            })
+        let (size, modified) = FileInfo::metadata(path);
+        found.results.push(FileInfo {
+            path: path.to_string_lossy().to_string(),
+            root: root.to_string(),
+            name: name.to_string_lossy().to_string(),
+            ext: path.extension().unwrap_or_default().to_str().unwrap_or_default().into(),
+            matches,
+            is_folder: fs_type.is_dir(),
+            plugin,
+            ranges: name_match.ranges,
+            score: name_match.score,
+            size,
+            modified,
         });
+        found.results.extend(entries);
+        found
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn find_contents(
-        text: &str,
-        dir: &str,
+        terms: &[ContentTerm],
+        dirs: &[String],
         allowed_files: &HashSet<String>,
         options: Options,
         global_search_id: Arc<AtomicUsize>,
         start_search_id: usize,
         total_search_count: Option<Arc<AtomicUsize>>, //only Some if find contents, else None because we would have counted the file in the name search
+        file_found: Option<&(dyn Fn(FileInfo) + Sync)>, //if Some, files are passed on as found instead of returned
+        index: Option<&ContentIndex>,
     ) -> ContentFileInfoResults {
-        let re = regex::RegexBuilder::new(text).case_insensitive(!options.content.case_sensitive).build();
-
         let content_results = rgtools::search_contents(
-            text,
-            &[OsString::from_str(dir).unwrap()],
+            terms,
+            &dirs.iter().map(OsString::from).collect::<Vec<_>>(),
             allowed_files,
-            options.content,
+            &options,
             global_search_id,
             start_search_id,
             total_search_count,
+            file_found,
+            index,
         );
-        let strings = content_results.results;
-        let errors = content_results.errors;
-
-        let file_line_content: Vec<Vec<&str>> = strings
-            .iter()
-            .map(|x| x.split(&SEPARATOR).collect::<Vec<&str>>())
-            .filter(|x| x.len() == 3)
-            .collect();
-        let mut hm: HashMap<String, FileInfo> = HashMap::new();
-        for f in file_line_content.iter() {
-            let (path, extended): (String, Option<ExtendedType>) = match f[0].split_once(EXTENSION_SEPARATOR) {
-                Some((a, b)) => (a.to_string(), Some(b.into())),
-                None => (f[0].to_string(), None),
+        ContentFileInfoResults {
+            results: content_results.results,
+            errors: content_results.errors,
+        }
+    }
+
+    ///Matches paths against the search as they change. Events are gathered until there is a pause (or a second passed),
+    ///as saving a file often causes several
+    fn watch_changes(
+        events: Receiver<notify::Event>,
+        search: &Search,
+        options: &Options,
+        file_sender: Sender<Message>,
+        watch_id: Arc<AtomicUsize>,
+        id: usize,
+    ) {
+        let roots: Vec<PathBuf> = search.roots().iter().map(PathBuf::from).collect();
+        if MetadataFilter::new(&options.name).is_err() {
+            return;
+        }
+        while let Ok(event) = events.recv() {
+            //path, and if it may be a new folder whose contents must be searched too
+            let mut changed: HashMap<PathBuf, bool> = HashMap::new();
+            let mut add = |event: notify::Event| {
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                let new = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) | EventKind::Any);
+                for path in event.paths {
+                    *changed.entry(path).or_default() |= new;
+                }
             };
-            let pb = PathBuf::from(&path);
-
-            let entry = hm.entry(path.clone()).or_insert(FileInfo {
-                path: path.clone(),
-                matches: vec![],
-                ext: pb.extension().unwrap_or(&OsString::from("")).to_str().unwrap_or_default().into(),
-                name: PathBuf::from(f[0]).file_name().unwrap_or_default().to_str().unwrap_or_default().into(),
-                is_folder: pb.is_dir(),
-                plugin: extended,
-                ranges: vec![],
-            });
-            let regex_matches = re.clone().unwrap().find_iter(f[2]).map(|a| a.range()).collect::<Vec<_>>();
-            entry.matches.push(Match {
-                line: f[1].parse().unwrap_or(0),
-                content: f[2].to_owned(),
-                ranges: regex_matches,
-            });
+            add(event);
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_secs(1)
+                && let Ok(event) = events.recv_timeout(Duration::from_millis(200))
+            {
+                add(event);
+            }
+
+            //built for each batch, as ages like 7d and today are from now
+            let Ok(filter) = MetadataFilter::new(&options.name) else {
+                return;
+            };
+            for (path, new) in changed {
+                if watch_id.load(Ordering::Relaxed) != id {
+                    return;
+                }
+                let Some(root) = Search::root_of(&path, &roots) else {
+                    continue;
+                };
+                let mut messages = vec![];
+                let mut errors = vec![];
+                Manager::match_changed(search, options, &filter, root, &path, &mut messages, &mut errors);
+                //globs and depths are relative to the root, not to the new folder, so they are left to `match_changed`
+                let walk_ops = NameOptions {
+                    globs: vec![],
+                    exclude_dirs: vec![],
+                    max_depth: None,
+                    min_depth: None,
+                    ..options.name.clone()
+                };
+                if new
+                    && path.is_dir()
+                    && let Ok(walker) = rgtools::walk_builder(&[path.clone().into_os_string()], &walk_ops)
+                {
+                    for dent in walker.build().skip(1) {
+                        match dent {
+                            Ok(dent) => Manager::match_changed(search, options, &filter, root, dent.path(), &mut messages, &mut errors),
+                            Err(err) => errors.push(SearchError::walk(err)),
+                        }
+                    }
+                }
+                if !errors.is_empty() {
+                    messages.push(Message::FileErrors(errors));
+                }
+                for mess in messages {
+                    if watch_id.load(Ordering::Relaxed) != id || file_sender.send(mess).is_err() {
+                        return;
+                    }
+                }
+            }
         }
-        ContentFileInfoResults {
-            results: hm.into_values().collect(),
-            errors,
+    }
+
+    ///what a changed path now matches, the same as in a full search
+    fn match_changed(
+        search: &Search,
+        options: &Options,
+        filter: &MetadataFilter,
+        root: &Path,
+        path: &Path,
+        messages: &mut Vec<Message>,
+        errors: &mut Vec<SearchError>,
+    ) {
+        let path_string = path.to_string_lossy().to_string();
+        let meta = if options.name.follow_links {
+            std::fs::metadata(path)
+        } else {
+            std::fs::symlink_metadata(path)
+        };
+        let Ok(meta) = meta else {
+            messages.push(Message::Watched(path_string, vec![], true));
+            return;
+        };
+        let fs_type = meta.file_type();
+        if rgtools::is_ignored(root, path, fs_type.is_dir(), &options.name) || !filter.matches(&meta) {
+            messages.push(Message::Watched(path_string, vec![], false));
+            return;
+        }
+        let root_string = root.to_string_lossy().to_string();
+        let found = if !search.name_text.is_empty() {
+            let matcher = match NameMatcher::new(&search.name_text, &options.name) {
+                Ok(matcher) => matcher,
+                Err(err) => {
+                    errors.push(SearchError::InvalidRegex {
+                        pattern: search.name_text.clone(),
+                        source: Arc::new(err),
+                    });
+                    return;
+                }
+            };
+            Manager::match_name(
+                search,
+                &matcher,
+                &root_string,
+                path,
+                fs_type,
+                options,
+                Arc::new(AtomicUsize::new(0)),
+                0,
+                None,
+            )
+        } else if search.has_contents() && fs_type.is_file() {
+            Manager::find_contents(
+                &search.content_terms(),
+                &[root_string],
+                &HashSet::from_iter([path_string.clone()]),
+                options.clone(),
+                Arc::new(AtomicUsize::new(0)),
+                0,
+                None,
+                None,
+                None,
+            )
+        } else {
+            ContentFileInfoResults::default()
+        };
+        errors.extend(found.errors);
+        messages.push(Message::Watched(path_string, found.results, false));
+    }
+
+    ///returns the index of dir (roots joined by `search::DIR_SEPARATOR`), loading it unless it is the one already in memory
+    fn load_index(current: &Mutex<Option<Arc<ContentIndex>>>, dir: &str) -> Arc<ContentIndex> {
+        let mut current = current.lock().unwrap();
+        match current.as_ref() {
+            Some(index) if index.is_root(dir) => index.clone(),
+            _ => {
+                let index = Arc::new(ContentIndex::load(dir));
+                *current = Some(index.clone());
+                index
+            }
+        }
+    }
+
+    ///sorts by `sort`, then by `then` where those are equal. `descending` reverses `sort` only
+    pub fn do_sort(vec: &mut [FileInfo], sort: Sort, then: Sort, descending: bool) {
+        if matches!((sort, then), (Sort::None, Sort::None)) {
+            return;
         }
+        vec.sort_by(|a, b| {
+            let first = Self::compare(a, b, sort);
+            let first = if descending { first.reverse() } else { first };
+            first.then_with(|| Self::compare(a, b, then))
+        });
     }
 
-    pub fn do_sort(vec: &mut [FileInfo], sort: Sort) {
+    ///ascending order of two results by a key, except relevance which is best first.
+    ///Results without a size or modified time come first
+    fn compare(a: &FileInfo, b: &FileInfo, sort: Sort) -> std::cmp::Ordering {
         match sort {
-            Sort::None => (),
-            Sort::Path => vec.sort_by(|a, b| a.path.cmp(&b.path)),
-            Sort::Name => vec.sort_by(|a, b| a.name.cmp(&b.name)),
-            Sort::Extension => vec.sort_by(|a, b| a.ext.cmp(&b.ext)),
-        };
+            Sort::None => std::cmp::Ordering::Equal,
+            Sort::Path => a.path.cmp(&b.path),
+            Sort::Name => a.name.cmp(&b.name),
+            Sort::Extension => a.ext.cmp(&b.ext),
+            Sort::Relevance => b.score.cmp(&a.score),
+            Sort::Size => a.size.cmp(&b.size),
+            Sort::Modified => a.modified.cmp(&b.modified),
+            Sort::Matches => a.match_count().cmp(&b.match_count()),
+            Sort::Depth => a.depth().cmp(&b.depth()),
+        }
     }
 }
 
 #[derive(Default)]
 pub struct ContentFileInfoResults {
     pub results: Vec<FileInfo>,
-    pub errors: Vec<String>,
+    pub errors: Vec<SearchError>,
 }
 
-fn message_receiver(internal_receiver: Receiver<Message>, external_sender: Sender<SearchResult>, ops: Arc<Mutex<Options>>) {
-    let mut final_names = vec![];
+///passes results on, and keeps them in the store. Stops the search once `Options::max_results` are found
+fn message_receiver(
+    internal_receiver: Receiver<Message>,
+    external_sender: Sender<SearchResult>,
+    ops: Arc<Mutex<Options>>,
+    current_search_id: Arc<AtomicUsize>,
+    results: SharedResults,
+    shared_only: Arc<AtomicBool>,
+) {
     let mut latest_number = 0;
     let mut tot_elapsed = Duration::from_secs(0);
+    let mut max_results = None;
+    let mut truncated = false;
     loop {
         let message = internal_receiver.recv();
         if message.is_err() {
@@ -435,22 +847,24 @@ fn message_receiver(internal_receiver: Receiver<Message>, external_sender: Sende
             Message::StartSearch(id) => {
                 latest_number = id;
                 tot_elapsed = Duration::from_secs(0);
-                final_names.clear();
-            }
-            Message::ContentFiles(files, number, elapsed) => {
-                if number == latest_number {
-                    //only update if new update (old updates are discarded)
-                    for f in files {
-                        final_names.push(f);
-                    }
-                    tot_elapsed += elapsed;
-                }
+                results.write().unwrap().start(id);
+                max_results = ops.lock().unwrap().max_results;
+                truncated = false;
             }
             Message::File(file, number) => {
                 //only update if new update (old updates are discarded)
                 if number == latest_number {
+                    let mut store = results.write().unwrap();
+                    //one more than the max, so the others are not searched. A newer search is not stopped
+                    if max_results.is_some_and(|max| store.len() >= max) {
+                        truncated = true;
+                        let _ = current_search_id.compare_exchange(latest_number, latest_number + 1, Ordering::Relaxed, Ordering::Relaxed);
+                        continue;
+                    }
                     //send to output
-                    final_names.push(file.clone());
+                    store.files.push(file.clone());
+                    store.changes += 1;
+                    drop(store);
                     //quietly ignore if no receiver because probably closed
                     let _ = external_sender.send(SearchResult::InterimResult(file));
                 }
@@ -459,20 +873,60 @@ fn message_receiver(internal_receiver: Receiver<Message>, external_sender: Sende
                 if number == latest_number {
                     tot_elapsed += elapsed.to_owned();
 
-                    let sort_type = ops.lock().unwrap().sort;
-                    Manager::do_sort(&mut final_names, sort_type);
-                    let results = SearchResult::FinalResults(FinalResults {
+                    let (sort, then, descending) = {
+                        let ops = ops.lock().unwrap();
+                        (ops.sort, ops.then_sort, ops.sort_descending)
+                    };
+                    let mut store = results.write().unwrap();
+                    store.sort(sort, then, descending);
+                    store.done = true;
+                    let data = if shared_only.load(Ordering::Relaxed) {
+                        vec![]
+                    } else {
+                        store.files.clone()
+                    };
+                    drop(store);
+                    let final_results = SearchResult::FinalResults(FinalResults {
                         id: latest_number,
-                        data: final_names.to_vec(),
+                        data,
                         duration: tot_elapsed,
                         stopped,
+                        truncated,
                     });
 
                     //send out to whoever is listening
-                    let _ = external_sender.send(results);
+                    let _ = external_sender.send(final_results);
                 }
             }
 
+            Message::Watched(path, files, removed) => {
+                //entries of archives, and files in a removed folder, belong to the path too
+                let entry_prefix = format!("{path}{}", extended::ENTRY_SEPARATOR);
+                let dir_prefix = format!("{path}{}", std::path::MAIN_SEPARATOR);
+                let mut store = results.write().unwrap();
+                let (old, kept): (Vec<FileInfo>, Vec<FileInfo>) = store
+                    .files
+                    .drain(..)
+                    .partition(|x| x.path == path || x.path.starts_with(&entry_prefix) || (removed && x.path.starts_with(&dir_prefix)));
+                store.files = kept;
+                let mut events = vec![];
+                for fi in files.iter() {
+                    match old.iter().find(|x| x.path == fi.path) {
+                        Some(x) if x == fi => {}
+                        Some(_) => events.push(WatchEvent::Updated(fi.clone())),
+                        None => events.push(WatchEvent::Added(fi.clone())),
+                    }
+                }
+                for x in old.into_iter().filter(|x| !files.iter().any(|fi| fi.path == x.path)) {
+                    events.push(WatchEvent::Removed(x.path));
+                }
+                store.files.extend(files);
+                store.changes += 1;
+                drop(store);
+                for event in events {
+                    let _ = external_sender.send(SearchResult::Watch(event));
+                }
+            }
             Message::Quit => break,
             Message::FileErrors(err) => {
                 // eprintln!("Err: {err:?}");
@@ -485,6 +939,7 @@ fn message_receiver(internal_receiver: Receiver<Message>, external_sender: Sende
     }
 }
 
+#[allow(clippy::collapsible_if)]
 fn get_or_create_settings_path() -> Option<String> {
     if let Some(mut dir) = dirs::config_dir() {
         dir.push("rusl");
@@ -556,26 +1011,28 @@ fn save_settings(ops: &Options) {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::mpsc::channel;
+    use std::sync::mpsc::{RecvError, channel};
 
     use super::*;
+    use crate::search::Occur;
 
     #[test]
     fn find_names() {
-        let file1 = add_demo_file();
+        let file1 = add_demo_file("rusltestdir");
 
         let (s, r) = channel();
         let mut man = Manager::new(s);
         let search = Search {
-            dir: file1.parent().unwrap().to_string_lossy().to_string(),
+            dirs: vec![file1.parent().unwrap().to_string_lossy().to_string()],
             name_text: file1.file_name().unwrap().to_string_lossy().to_string(),
             contents_text: "41".to_string(),
+            terms: vec![],
         };
         println!("using search {search:?}");
-        man.search(&search);
+        man.search(&search).unwrap();
 
         //first get interim
-        let mess = r.recv();
+        let mess = recv_result(&r);
         println!("mess {mess:?}");
         if let Ok(mess) = mess {
             println!("{mess:?}");
@@ -587,7 +1044,7 @@ mod tests {
             }
         }
 
-        let mess = r.recv();
+        let mess = recv_result(&r);
         println!("mess {mess:?}");
         if let Ok(mess) = mess {
             println!("{mess:?}");
@@ -600,12 +1057,256 @@ mod tests {
         }
     }
 
-    fn add_demo_file() -> PathBuf {
+    #[test]
+    #[allow(clippy::too_many_arguments)]
+    fn find_contents() {
+        let file1 = add_demo_file("rusltestdircontents");
+
+        let (s, r) = channel();
+        let mut man = Manager::new(s);
+        let search = Search {
+            dirs: vec![file1.parent().unwrap().to_string_lossy().to_string()],
+            name_text: String::new(),
+            contents_text: "41".to_string(),
+            terms: vec![],
+        };
+        man.search(&search).unwrap();
+
+        loop {
+            match recv_result(&r) {
+                Ok(SearchResult::FinalResults(fr)) => {
+                    assert_eq!(fr.data.len(), 1);
+                    let mat = &fr.data[0].matches[0];
+                    assert_eq!(mat.line, 2);
+                    assert_eq!(mat.offset, 6);
+                    assert_eq!(mat.content, "there 41 go");
+                    assert_eq!(mat.ranges, vec![6..8]);
+                    break;
+                }
+                Ok(_) => {}
+                Err(err) => panic!("{err}"),
+            }
+        }
+    }
+
+    #[test]
+    fn multiple_roots() {
+        let dir1 = add_demo_file("rusltestdirroots1").parent().unwrap().to_string_lossy().to_string();
+        let dir2 = add_demo_file("rusltestdirroots2").parent().unwrap().to_string_lossy().to_string();
+
+        let (s, r) = channel();
+        let mut man = Manager::new(s);
+        //the repeated dir is only searched once
+        man.search(&Search {
+            dirs: vec![dir1.clone(), dir2.clone(), dir1.clone()],
+            name_text: String::new(),
+            contents_text: "41".to_string(),
+            terms: vec![],
+        })
+        .unwrap();
+        loop {
+            if let Ok(SearchResult::FinalResults(mut fr)) = recv_result(&r) {
+                fr.data.sort_by(|a, b| a.root.cmp(&b.root));
+                let roots: Vec<&str> = fr.data.iter().map(|x| x.root.as_str()).collect();
+                assert_eq!(roots, vec![dir1.as_str(), dir2.as_str()]);
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn content_terms() {
+        let file1 = add_demo_file("rusltestdirterms");
+        let dir = file1.parent().unwrap();
+        std::fs::write(dir.join("a.rs"), "use tokio;\nunsafe {}").unwrap();
+        std::fs::write(dir.join("b.rs"), "use tokio;\nunsafe {}\n#[test]").unwrap();
+        std::fs::write(dir.join("c.rs"), "use tokio;").unwrap();
+
+        let (s, r) = channel();
+        let mut man = Manager::new(s);
+        man.search(&Search {
+            dirs: vec![dir.to_string_lossy().to_string()],
+            name_text: String::new(),
+            contents_text: "tokio".to_string(),
+            terms: vec![
+                ContentTerm::new("unsafe", Occur::Must),
+                ContentTerm::new("#\\[test\\]", Occur::MustNot),
+                ContentTerm::new("nothing", Occur::Should),
+                ContentTerm::new("\\{\\}", Occur::Should),
+            ],
+        })
+        .unwrap();
+        loop {
+            if let Ok(SearchResult::FinalResults(fr)) = recv_result(&r) {
+                assert_eq!(fr.data.len(), 1);
+                assert_eq!(fr.data[0].name, "a.rs");
+                //matches of each term, in line order
+                let terms: Vec<usize> = fr.data[0].matches.iter().map(|x| x.term).collect();
+                assert_eq!(terms, vec![0, 1, 4]);
+                break;
+            }
+        }
+
+        //an invalid pattern is reported with its position, before searching
+        let search = Search {
+            name_text: "main".to_string(),
+            terms: vec![ContentTerm::new("unsafe", Occur::Must), ContentTerm::new("fn (x", Occur::Must)],
+            ..Default::default()
+        };
+        let err = man.search(&search).unwrap_err();
+        assert_eq!(err.field, PatternField::Term(1));
+        assert_eq!(err.message, "unclosed group");
+        assert_eq!(err.column(), Some(4));
+    }
+
+    #[test]
+    fn limits() {
+        let file1 = add_demo_file("rusltestdirlimits");
+        let dir = file1.parent().unwrap();
+        for i in 0..5 {
+            std::fs::write(dir.join(format!("{i}.txt")), "41 first\n41 second").unwrap();
+        }
+
+        let (s, r) = channel();
+        let mut man = Manager::new(s);
+        let mut ops = man.get_options();
+        ops.max_results = Some(2);
+        ops.content.max_count = Some(1);
+        ops.content.max_line_len = Some(4);
+        man.set_options(ops);
+        man.search(&Search {
+            dirs: vec![dir.to_string_lossy().to_string()],
+            name_text: String::new(),
+            contents_text: "first|second".to_string(),
+            terms: vec![],
+        })
+        .unwrap();
+        loop {
+            if let Ok(SearchResult::FinalResults(fr)) = recv_result(&r) {
+                assert_eq!(fr.data.len(), 2);
+                assert!(fr.truncated);
+                let mat = &fr.data[0].matches;
+                assert_eq!(mat.len(), 1);
+                assert_eq!(mat[0].content, "41 f");
+                assert_eq!(mat[0].ranges, vec![3..4]);
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn sort_keys() {
+        let now = std::time::SystemTime::now();
+        let file = |path: &str, size: u64, age: u64| FileInfo {
+            size: Some(size),
+            modified: Some(now - Duration::from_secs(age)),
+            ..FileInfo::fixture(path, "dir")
+        };
+        let mut files = vec![
+            file("dir/a/b/c", 1, 10),
+            file("dir/d", 3, 20),
+            file("dir/a/e", 2, 5),
+            file("dir/f", 3, 30),
+        ];
+        let paths = |files: &[FileInfo]| files.iter().map(|x| x.path.clone()).collect::<Vec<String>>();
+
+        //most recently changed first
+        Manager::do_sort(&mut files, Sort::Modified, Sort::None, true);
+        assert_eq!(paths(&files), vec!["dir/a/e", "dir/a/b/c", "dir/d", "dir/f"]);
+
+        //largest first, then by path for the same size
+        Manager::do_sort(&mut files, Sort::Size, Sort::Path, true);
+        assert_eq!(paths(&files), vec!["dir/d", "dir/f", "dir/a/e", "dir/a/b/c"]);
+
+        Manager::do_sort(&mut files, Sort::Depth, Sort::None, false);
+        assert_eq!(files.iter().map(|x| x.depth()).collect::<Vec<usize>>(), vec![1, 1, 2, 3]);
+    }
+
+    #[test]
+    fn shared() {
+        let file1 = add_demo_file("rusltestdirshared");
+        let dir = file1.parent().unwrap();
+        std::fs::write(dir.join("b.txt"), "41").unwrap();
+
+        let (s, r) = channel();
+        let mut man = Manager::new(s);
+        man.set_shared_only(true);
+        man.search(&Search {
+            dirs: vec![dir.to_string_lossy().to_string()],
+            name_text: String::new(),
+            contents_text: "41".to_string(),
+            terms: vec![],
+        })
+        .unwrap();
+        loop {
+            if let Ok(SearchResult::FinalResults(fr)) = recv_result(&r) {
+                assert!(fr.data.is_empty());
+                let results = man.results();
+                let store = results.read().unwrap();
+                assert!(store.is_done());
+                assert_eq!(store.id(), fr.id);
+                assert_eq!(store.len(), 2);
+                assert_eq!(store.page(0, 1).len(), 1);
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn watch() {
+        let file1 = add_demo_file("rusltestdirwatch");
+        let dir = file1.parent().unwrap().to_path_buf();
+
+        let (s, r) = channel();
+        let mut man = Manager::new(s);
+        man.search(&Search {
+            dirs: vec![dir.to_string_lossy().to_string()],
+            name_text: String::new(),
+            contents_text: "41".to_string(),
+            terms: vec![],
+        })
+        .unwrap();
+        loop {
+            if let Ok(SearchResult::FinalResults(fr)) = recv_result(&r) {
+                assert_eq!(fr.data.len(), 1);
+                break;
+            }
+        }
+        man.watch().unwrap();
+
+        let next_event = || loop {
+            match r.recv_timeout(Duration::from_secs(10)) {
+                Ok(SearchResult::Watch(event)) => return event,
+                Ok(_) => {}
+                Err(err) => panic!("{err}"),
+            }
+        };
+        let file2 = dir.join("new.txt");
+        std::fs::write(&file2, "41 again").unwrap();
+        assert!(matches!(next_event(), WatchEvent::Added(fi) if fi.path == file2.to_string_lossy()));
+        std::fs::write(&file1, "41 41\nmore").unwrap();
+        assert!(matches!(next_event(), WatchEvent::Updated(fi) if fi.path == file1.to_string_lossy()));
+        std::fs::remove_file(&file2).unwrap();
+        assert!(matches!(next_event(), WatchEvent::Removed(path) if path == file2.to_string_lossy()));
+        man.unwatch();
+    }
+
+    ///skip the search counts, which are sent regularly
+    fn recv_result(r: &Receiver<SearchResult>) -> Result<SearchResult, RecvError> {
+        loop {
+            match r.recv()? {
+                SearchResult::SearchCount(_) => {}
+                mess => return Ok(mess),
+            }
+        }
+    }
+
+    fn add_demo_file(dir_name: &str) -> PathBuf {
         let mut dir = std::env::temp_dir();
         println!("Using Temporary directory: {}", dir.display());
 
         //create new directory in here, and create a file with the relevant text
-        dir.push("rusltestdir");
+        dir.push(dir_name);
         if dir.exists() {
             let _ = std::fs::remove_dir_all(&dir);
         }
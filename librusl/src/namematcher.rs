@@ -0,0 +1,88 @@
+use std::ops::Range;
+
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use regex::{Regex, RegexBuilder};
+
+use crate::options::NameOptions;
+
+///Matches file names against the name search, as a regex, or as an fzf style fuzzy pattern if `NameOptions::fuzzy`
+pub enum NameMatcher {
+    Regex(Regex),
+    Fuzzy(Box<SkimMatcherV2>, String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameMatch {
+    pub score: Option<i64>,        //only for fuzzy, higher is better
+    pub ranges: Vec<Range<usize>>, //byte ranges of the name that matched
+}
+
+impl NameMatcher {
+    pub fn new(text: &str, ops: &NameOptions) -> Result<Self, regex::Error> {
+        if ops.fuzzy {
+            let matcher = SkimMatcherV2::default();
+            let matcher = if ops.case_sensitive {
+                matcher.respect_case()
+            } else {
+                matcher.ignore_case()
+            };
+            return Ok(Self::Fuzzy(Box::new(matcher), text.to_string()));
+        }
+        RegexBuilder::new(text).case_insensitive(!ops.case_sensitive).build().map(Self::Regex)
+    }
+
+    ///None if the name does not match
+    pub fn find(&self, name: &str) -> Option<NameMatch> {
+        match self {
+            Self::Regex(re) => re.is_match(name).then(|| NameMatch {
+                score: None,
+                ranges: re.find_iter(name).map(|x| x.range()).collect(),
+            }),
+            Self::Fuzzy(matcher, pattern) => {
+                let (score, indices) = matcher.fuzzy_indices(name, pattern)?;
+                Some(NameMatch {
+                    score: Some(score),
+                    ranges: char_ranges(name, &indices),
+                })
+            }
+        }
+    }
+}
+
+///byte ranges of the chars at `indices` (sorted), with neighbouring chars merged into one range
+fn char_ranges(text: &str, indices: &[usize]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = vec![];
+    let mut indices = indices.iter().peekable();
+    for (i, (start, ch)) in text.char_indices().enumerate() {
+        if indices.next_if(|x| **x == i).is_none() {
+            continue;
+        }
+        let end = start + ch.len_utf8();
+        match ranges.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy() {
+        let ops = NameOptions {
+            fuzzy: true,
+            ..Default::default()
+        };
+        let matcher = NameMatcher::new("mnrs", &ops).unwrap();
+        let found = matcher.find("main.rs").unwrap();
+        assert_eq!(found.ranges, vec![0..1, 3..4, 5..7]);
+        assert!(matcher.find("lib.rs").is_none());
+        //better matches score higher
+        assert!(matcher.find("mnrs.txt").unwrap().score > found.score);
+        //ranges are in bytes
+        assert_eq!(NameMatcher::new("éb", &ops).unwrap().find("aébc").unwrap().ranges, vec![1..4]);
+    }
+}
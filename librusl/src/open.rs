@@ -0,0 +1,85 @@
+use std::{path::Path, process::Command, thread};
+
+///A result to open: a file (at a line of a content match), or the folder containing a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpenTarget {
+    File(String, Option<usize>),
+    Folder(String),
+}
+
+impl OpenTarget {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::File(path, _) | Self::Folder(path) => path,
+        }
+    }
+
+    ///Opens files with the `Options::editor` command, e.g. `code -g {path}:{line}` or `nvim +{line} {path}`.
+    ///Without an editor, and for folders, the default program of the system is used
+    pub fn open(&self, editor: &str) -> Result<(), String> {
+        match self {
+            Self::File(path, line) if !editor.trim().is_empty() => run(editor_command(editor, path, *line)),
+            Self::File(path, _) => run(system_command(path)),
+            Self::Folder(path) => {
+                let path = Path::new(path);
+                let folder = if path.is_dir() {
+                    path
+                } else {
+                    path.parent().filter(|x| !x.as_os_str().is_empty()).unwrap_or(Path::new("."))
+                };
+                run(system_command(&folder.to_string_lossy()))
+            }
+        }
+    }
+}
+
+///program and arguments split by spaces, with `{path}` and `{line}` (1 if none) replaced.
+///The path is added at the end if there is no `{path}`
+fn editor_command(editor: &str, path: &str, line: Option<usize>) -> Vec<String> {
+    let line = line.unwrap_or(1).to_string();
+    let mut command: Vec<String> = editor
+        .split_whitespace()
+        .map(|x| x.replace("{path}", path).replace("{line}", &line))
+        .collect();
+    if !editor.contains("{path}") {
+        command.push(path.to_string());
+    }
+    command
+}
+
+fn system_command(path: &str) -> Vec<String> {
+    let program = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    vec![program.to_string(), path.to_string()]
+}
+
+fn run(command: Vec<String>) -> Result<(), String> {
+    let (program, args) = command.split_first().ok_or("Empty editor command")?;
+    let mut child = Command::new(program)
+        .args(args)
+        .spawn()
+        .map_err(|e| format!("Could not run {program}: {e}"))?;
+    //wait in the background so it does not linger once it exits
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editor() {
+        assert_eq!(
+            editor_command("code -g {path}:{line}", "my dir/a.rs", Some(12)),
+            vec!["code", "-g", "my dir/a.rs:12"]
+        );
+        assert_eq!(editor_command("nvim +{line} {path}", "a.rs", None), vec!["nvim", "+1", "a.rs"]);
+        assert_eq!(editor_command("gedit", "a.rs", Some(3)), vec!["gedit", "a.rs"]);
+    }
+}
@@ -1,30 +1,123 @@
 use serde::{Deserialize, Serialize};
+
+use crate::{extended::CommandExtractor, search::Search};
+
+///results the GUIs show when `Options::max_results` is not set, see `Options::shown_results`
+pub const SHOWN_RESULTS: usize = 1000;
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Options {
     //general options
     #[serde(default)]
     pub sort: Sort,
     #[serde(default)]
-    pub last_dir: String,
+    pub then_sort: Sort, //for results that are equal by `sort`
+    #[serde(default)]
+    pub sort_descending: bool, //reverses `sort`, `then_sort` is always ascending
+    #[serde(default)]
+    pub last_dir: String, //dirs of the last search, joined by `search::DIR_SEPARATOR`
     #[serde(default)]
     pub name_history: Vec<String>,
     #[serde(default)]
     pub content_history: Vec<String>,
     #[serde(default)]
+    pub editor: String, //command results are opened with e.g. code -g {path}:{line}, see `OpenTarget::open`
+    #[serde(default)]
+    pub max_results: Option<usize>, //the search stops once this many files are found, see `FinalResults::truncated`
+    #[serde(default)]
     pub name: NameOptions,
     #[serde(default)]
     pub content: ContentOptions,
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
             sort: Sort::None,
+            then_sort: Sort::None,
+            sort_descending: false,
             last_dir: ".".to_string(),
             name_history: vec![],
             content_history: vec![],
+            editor: String::new(),
+            max_results: None,
             name: Default::default(),
             content: Default::default(),
+            saved_searches: vec![],
+        }
+    }
+}
+
+impl Options {
+    ///results the GUIs show at once: all of them if `max_results` is set, as the search stops there, otherwise `SHOWN_RESULTS`
+    pub fn shown_results(&self) -> usize {
+        self.max_results.unwrap_or(SHOWN_RESULTS)
+    }
+
+    pub fn saved_search(&self, name: &str) -> Option<&SavedSearch> {
+        self.saved_searches.iter().find(|x| x.name == name)
+    }
+
+    ///adds the saved search, or replaces the one with the same name
+    pub fn add_saved_search(&mut self, saved: SavedSearch) {
+        match self.saved_searches.iter_mut().find(|x| x.name == saved.name) {
+            Some(old) => *old = saved,
+            None => self.saved_searches.push(saved),
+        }
+    }
+
+    ///false if there was none with the name
+    pub fn remove_saved_search(&mut self, name: &str) -> bool {
+        let len = self.saved_searches.len();
+        self.saved_searches.retain(|x| x.name != name);
+        len != self.saved_searches.len()
+    }
+
+    ///uses the sort, name and content options of a saved search. User defined extractors are kept
+    pub fn load_saved_search(&mut self, saved: &SavedSearch) {
+        let extractors = std::mem::take(&mut self.content.extractors);
+        self.sort = saved.sort;
+        self.then_sort = saved.then_sort;
+        self.sort_descending = saved.sort_descending;
+        self.name = saved.name_options.clone();
+        self.content = saved.content_options.clone();
+        self.content.extractors = extractors;
+    }
+}
+
+///A search and the options it was run with, saved under a name to run again
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SavedSearch {
+    pub name: String,
+    #[serde(default)]
+    pub search: Search,
+    #[serde(default)]
+    pub sort: Sort,
+    #[serde(default)]
+    pub then_sort: Sort,
+    #[serde(default)]
+    pub sort_descending: bool,
+    #[serde(default)]
+    pub name_options: NameOptions,
+    #[serde(default)]
+    pub content_options: ContentOptions,
+}
+
+impl SavedSearch {
+    pub fn new(name: &str, search: &Search, ops: &Options) -> Self {
+        let mut content_options = ops.content.clone();
+        //extractors are general settings, not part of a search
+        content_options.extractors.clear();
+        Self {
+            name: name.to_string(),
+            search: search.clone(),
+            sort: ops.sort,
+            then_sort: ops.then_sort,
+            sort_descending: ops.sort_descending,
+            name_options: ops.name.clone(),
+            content_options,
         }
     }
 }
@@ -43,6 +136,26 @@ pub struct NameOptions {
     pub ignore_dot: bool,
     #[serde(default = "bool_true")]
     pub use_gitignore: bool,
+    #[serde(default)]
+    pub size: String, //e.g. >10MB, see `MetadataFilter`
+    #[serde(default)]
+    pub modified: String, //e.g. today, last 7 days, <2024-01-31
+    #[serde(default)]
+    pub created: String,
+    #[serde(default)]
+    pub globs: Vec<String>, //ripgrep style globs, e.g. *.rs, or !target to exclude
+    #[serde(default)]
+    pub types: Vec<String>, //file types e.g. rust, or !js to exclude
+    #[serde(default)]
+    pub type_defs: Vec<String>, //extra file types e.g. web:*.{html,css}
+    #[serde(default)]
+    pub max_depth: Option<usize>, //1 is only the files in the searched directory
+    #[serde(default)]
+    pub min_depth: Option<usize>,
+    #[serde(default)]
+    pub exclude_dirs: Vec<String>, //directories not walked into, e.g. node_modules, .cache, target/debug
+    #[serde(default)]
+    pub fuzzy: bool, //match names fzf style instead of as a regex, see `NameMatcher`
 }
 
 fn bool_true() -> bool {
@@ -58,6 +171,16 @@ impl Default for NameOptions {
             follow_links: false,
             ignore_dot: true,
             use_gitignore: true,
+            size: String::new(),
+            modified: String::new(),
+            created: String::new(),
+            globs: vec![],
+            types: vec![],
+            type_defs: vec![],
+            max_depth: None,
+            min_depth: None,
+            exclude_dirs: vec![],
+            fuzzy: false,
         }
     }
 }
@@ -70,6 +193,20 @@ pub struct ContentOptions {
     pub extended: bool,
     #[serde(default)]
     pub nonregex: bool, //--fixed-string
+    #[serde(default)]
+    pub multiline: bool, //allow matches to span lines (-U)
+    #[serde(default)]
+    pub before_context: usize, //lines shown before each match (-B)
+    #[serde(default)]
+    pub after_context: usize, //lines shown after each match (-A)
+    #[serde(default)]
+    pub extractors: Vec<CommandExtractor>, //user defined extractors, used along with the registered ones
+    #[serde(default)]
+    pub use_index: bool, //keep an index of each searched directory in the config dir, to skip files on later searches
+    #[serde(default)]
+    pub max_count: Option<usize>, //matching lines kept of each file, for each term (-m)
+    #[serde(default)]
+    pub max_line_len: Option<usize>, //chars kept of each line, the rest is cut off
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, Default)]
@@ -79,6 +216,11 @@ pub enum Sort {
     Path,
     Name,
     Extension,
+    Relevance, //best fuzzy name matches first
+    Size,
+    Modified,
+    Matches, //number of matching lines
+    Depth,   //below the searched dir
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
@@ -88,3 +230,42 @@ pub enum FTypes {
     #[default]
     All,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{ContentTerm, Occur};
+
+    #[test]
+    fn saved_searches() {
+        let mut ops = Options::default();
+        ops.name.fuzzy = true;
+        ops.content.extractors.push(CommandExtractor {
+            name: "Notebook".to_string(),
+            extensions: vec!["ipynb".to_string()],
+            command: "cat {path}".to_string(),
+        });
+        let search = Search {
+            dirs: vec!["src".to_string(), "tests".to_string()],
+            name_text: "rs".to_string(),
+            contents_text: "fn".to_string(),
+            terms: vec![ContentTerm::new("unsafe", Occur::MustNot)],
+        };
+        ops.add_saved_search(SavedSearch::new("safe", &search, &ops));
+        assert!(ops.saved_searches[0].content_options.extractors.is_empty());
+
+        //kept in the config
+        let mut ops: Options = toml::from_str(&toml::to_string_pretty(&ops).unwrap()).unwrap();
+        let saved = ops.saved_search("safe").unwrap().clone();
+        assert_eq!(saved.search.dirs, search.dirs);
+        assert_eq!(saved.search.terms, search.terms);
+
+        ops.name.fuzzy = false;
+        ops.load_saved_search(&saved);
+        assert!(ops.name.fuzzy);
+        assert_eq!(ops.content.extractors.len(), 1);
+
+        assert!(ops.remove_saved_search("safe"));
+        assert!(ops.saved_search("safe").is_none());
+    }
+}
@@ -0,0 +1,293 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use regex::{NoExpand, Regex, RegexBuilder};
+
+use crate::manager::FinalResults;
+use crate::options::ContentOptions;
+
+///A replacement of a content search, which can be previewed before it is applied
+#[derive(Debug, Clone, Default)]
+pub struct ReplacePreview {
+    pub files: Vec<FileReplacement>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileReplacement {
+    pub path: String,
+    pub changes: Vec<LineChange>,
+    new_content: String,
+    modified: Option<SystemTime>, //to check file was not changed since preview
+}
+
+///Lines before and after the replacement. For multiline matches, before and after may span more than one line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineChange {
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+///Files that were changed, with their backups (if any) so that the replacement can be undone
+#[derive(Debug, Clone, Default)]
+pub struct ReplaceApplied {
+    pub files: Vec<String>,
+    pub backups: Vec<(String, PathBuf)>,
+    pub errors: Vec<String>,
+}
+
+impl ReplacePreview {
+    pub fn change_count(&self) -> usize {
+        self.files.iter().map(|x| x.changes.len()).sum()
+    }
+}
+
+///Replaces `pattern` with `replacement` in each of the result files, without writing anything.
+///The replacement can use capture groups e.g. `$1` or `${name}`, unless the options are nonregex.
+pub fn preview(results: &FinalResults, pattern: &str, replacement: &str, ops: &ContentOptions) -> Result<ReplacePreview, regex::Error> {
+    let mut pattern = pattern.to_string();
+    if ops.nonregex {
+        pattern = regex::escape(&pattern);
+    }
+    //multi_line so that ^ and $ match at lines like grep does
+    let re = RegexBuilder::new(&pattern)
+        .case_insensitive(!ops.case_sensitive)
+        .multi_line(true)
+        .build()?;
+
+    let mut preview = ReplacePreview::default();
+    //extended files are extracted text, so cannot be written back
+    for fi in results.data.iter().filter(|x| !x.is_folder && x.plugin.is_none()) {
+        let path = Path::new(&fi.path);
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                preview.errors.push(format!("Could not read {}: {err}", fi.path));
+                continue;
+            }
+        };
+        let (new_content, changes) = if ops.multiline {
+            replace_multiline(&re, &text, replacement, ops.nonregex)
+        } else {
+            replace_lines(&re, &text, replacement, ops.nonregex)
+        };
+        if changes.is_empty() {
+            continue;
+        }
+        preview.files.push(FileReplacement {
+            path: fi.path.clone(),
+            changes,
+            new_content,
+            modified: fs::metadata(path).and_then(|m| m.modified()).ok(),
+        });
+    }
+    Ok(preview)
+}
+
+///Writes the previewed replacements. Each file is written to a temporary file which is then renamed over the original,
+///so a file is either fully replaced or not at all. If `backup`, the originals are copied to a dir in the temp dir, for `undo`
+pub fn apply(preview: &ReplacePreview, backup: bool) -> ReplaceApplied {
+    let mut applied = ReplaceApplied::default();
+    let backup_dir = backup_dir();
+    if backup && let Err(err) = fs::create_dir_all(&backup_dir) {
+        applied
+            .errors
+            .push(format!("Could not create backup dir {}: {err}", backup_dir.display()));
+        return applied;
+    }
+    for (i, file) in preview.files.iter().enumerate() {
+        let path = Path::new(&file.path);
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified != file.modified {
+            applied.errors.push(format!("{} changed since preview, skipped", file.path));
+            continue;
+        }
+        if backup {
+            let backup_path = backup_dir.join(format!("{i}-{}", path.file_name().unwrap_or_default().to_string_lossy()));
+            if let Err(err) = fs::copy(path, &backup_path) {
+                applied.errors.push(format!("Could not back up {}: {err}", file.path));
+                continue;
+            }
+            applied.backups.push((file.path.clone(), backup_path));
+        }
+        match write_atomic(path, &file.new_content) {
+            Ok(()) => applied.files.push(file.path.clone()),
+            Err(err) => applied.errors.push(format!("Could not write {}: {err}", file.path)),
+        }
+    }
+    applied
+}
+
+///Restores the backups of an applied replacement, and removes them. Returns errors of files that could not be restored
+pub fn undo(applied: &ReplaceApplied) -> Vec<String> {
+    let mut errors = vec![];
+    for (path, backup) in applied.backups.iter() {
+        //copied, as the temp dir may be on another file system
+        match fs::read_to_string(backup).and_then(|original| write_atomic(Path::new(path), &original)) {
+            Ok(()) => {
+                let _ = fs::remove_file(backup);
+            }
+            Err(err) => errors.push(format!("Could not restore {path}: {err}")),
+        }
+    }
+    if let Some(dir) = applied.backups.first().and_then(|(_, backup)| backup.parent()) {
+        let _ = fs::remove_dir(dir);
+    }
+    errors
+}
+
+///a new dir for the backups of one apply
+fn backup_dir() -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir()
+        .join("rusl-backups")
+        .join(format!("{}-{nanos}-{count}", std::process::id()))
+}
+
+fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".rusl.tmp");
+    let temp = path.with_file_name(name);
+    fs::write(&temp, content)?;
+    //keep permissions of original
+    if let Ok(meta) = fs::metadata(path) {
+        fs::set_permissions(&temp, meta.permissions())?;
+    }
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+fn replace<'a>(re: &Regex, text: &'a str, replacement: &str, literal: bool) -> Cow<'a, str> {
+    if literal {
+        re.replace_all(text, NoExpand(replacement))
+    } else {
+        re.replace_all(text, replacement)
+    }
+}
+
+///replaces each line on its own, like the line oriented search
+fn replace_lines(re: &Regex, text: &str, replacement: &str, literal: bool) -> (String, Vec<LineChange>) {
+    let mut out = String::with_capacity(text.len());
+    let mut changes = vec![];
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let new = replace(re, body, replacement, literal);
+        if new != body {
+            changes.push(LineChange {
+                line: i + 1,
+                before: body.to_string(),
+                after: new.to_string(),
+            });
+        }
+        out.push_str(&new);
+        out.push_str(&line[body.len()..]);
+    }
+    (out, changes)
+}
+
+///replaces matches across lines. Each change covers the full lines a match touches
+fn replace_multiline(re: &Regex, text: &str, replacement: &str, literal: bool) -> (String, Vec<LineChange>) {
+    //merge matches that touch the same lines into one span
+    let mut spans: Vec<std::ops::Range<usize>> = vec![];
+    for m in re.find_iter(text) {
+        let start = text[..m.start()].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let end = if m.as_str().ends_with('\n') {
+            m.end() - 1
+        } else {
+            text[m.end()..].find('\n').map(|i| m.end() + i).unwrap_or(text.len())
+        };
+        match spans.last_mut() {
+            Some(prev) if start <= prev.end => prev.end = prev.end.max(end),
+            _ => spans.push(start..end),
+        }
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut changes = vec![];
+    let mut last = 0;
+    for span in spans {
+        out.push_str(&text[last..span.start]);
+        let before = &text[span.clone()];
+        let after = replace(re, before, replacement, literal);
+        if after != before {
+            changes.push(LineChange {
+                line: text[..span.start].matches('\n').count() + 1,
+                before: before.to_string(),
+                after: after.to_string(),
+            });
+        }
+        out.push_str(&after);
+        last = span.end;
+    }
+    out.push_str(&text[last..]);
+    (out, changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::fileinfo::FileInfo;
+
+    #[test]
+    fn preview_apply_undo() {
+        let mut dir = std::env::temp_dir();
+        dir.push("rusltestdirreplace");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("replace.txt");
+        let original = "let a = foo(1);\nlet b = bar(2);\nlet c = foo(3);\n";
+        fs::write(&file, original).unwrap();
+
+        let results = FinalResults {
+            data: vec![FileInfo::fixture(&file.to_string_lossy(), &dir.to_string_lossy())],
+            duration: Duration::from_secs(0),
+            id: 0,
+            stopped: false,
+            truncated: false,
+        };
+        let preview = preview(&results, r"foo\((\d)\)", "baz($1, 0)", &ContentOptions::default()).unwrap();
+        assert_eq!(preview.change_count(), 2);
+        assert_eq!(
+            preview.files[0].changes[1],
+            LineChange {
+                line: 3,
+                before: "let c = foo(3);".to_string(),
+                after: "let c = baz(3, 0);".to_string(),
+            }
+        );
+        //nothing written yet
+        assert_eq!(fs::read_to_string(&file).unwrap(), original);
+
+        let applied = apply(&preview, true);
+        assert!(applied.errors.is_empty());
+        //backups are not left next to the file
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "let a = baz(1, 0);\nlet b = bar(2);\nlet c = baz(3, 0);\n"
+        );
+
+        assert!(undo(&applied).is_empty());
+        assert_eq!(fs::read_to_string(&file).unwrap(), original);
+    }
+
+    #[test]
+    fn multiline() {
+        let re = RegexBuilder::new(r"fn (\w+)\(\n\s+self").multi_line(true).build().unwrap();
+        let (out, changes) = replace_multiline(&re, "a\nfn x(\n    self)\nb\n", "fn $1(&self", false);
+        assert_eq!(out, "a\nfn x(&self)\nb\n");
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].line, 2);
+        assert_eq!(changes[0].before, "fn x(\n    self)");
+    }
+}
@@ -1,182 +1,787 @@
 use crate::{
-    extended::{ExtendedTrait, ExtendedType},
-    options::ContentOptions,
+    error::SearchError,
+    extended::{self, ExtendedTrait},
+    fileinfo::{FileInfo, Match},
+    filter::MetadataFilter,
+    index::{ContentIndex, Extracted, FileKey, IndexEntry, IndexQuery, MAX_INDEXED_SIZE},
+    options::{ContentOptions, NameOptions, Options},
+    search::{self, ContentTerm, Occur, Search},
 };
 use grep::{
-    printer::{Standard, StandardBuilder},
+    matcher::Matcher,
     regex::{RegexMatcher, RegexMatcherBuilder},
-    searcher::{BinaryDetection, Searcher, SearcherBuilder},
+    searcher::{BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkMatch},
+};
+use ignore::{
+    WalkBuilder, WalkState,
+    gitignore::{Gitignore, GitignoreBuilder},
+    overrides::{Override, OverrideBuilder},
+    types::{Types, TypesBuilder},
 };
 use std::{
     collections::HashSet,
     ffi::OsString,
     fs::File,
-    io::{Cursor, Write},
+    io::Seek,
     path::Path,
     sync::{
+        Arc, Mutex,
         atomic::{AtomicUsize, Ordering},
-        Arc,
     },
 };
-use termcolor::NoColor;
-use walkdir::WalkDir;
-
-struct MyWrite {
-    data: Vec<u8>,
-}
-
-pub const SEPARATOR: &str = r"\0\1\2\3\4";
-pub const EXTENSION_SEPARATOR: &str = r"\5\6\7\8";
 
 #[derive(Default)]
 pub struct ContentResults {
-    pub results: Vec<String>,
-    pub errors: Vec<String>,
+    pub results: Vec<FileInfo>,
+    pub errors: Vec<SearchError>,
+}
+///matcher of a content term, with the trigrams the index needs to skip files
+#[derive(Clone)]
+struct TermMatcher {
+    matcher: RegexMatcher,
+    occur: Occur,
+    query: IndexQuery,
 }
+
+///searches the contents of files under `paths` (or only `allowed_files`) for the terms, evaluated per file
+#[allow(clippy::too_many_arguments)]
 pub fn search_contents(
-    pattern: &str,
+    terms: &[ContentTerm],
     paths: &[OsString],
     allowed_files: &HashSet<String>,
-    ops: ContentOptions,
+    options: &Options,
     global_search_id: Arc<AtomicUsize>,
     start_search_id: usize,
     total_search_count: Option<Arc<AtomicUsize>>, //only Some if find contents, else None because we would have counted the file in the name search
+    file_found: Option<&(dyn Fn(FileInfo) + Sync)>, //if Some, files are passed on as found instead of returned
+    index: Option<&ContentIndex>,                 //if Some, used to skip files, and updated with files that changed
 ) -> ContentResults {
-    let case_insensitive = !ops.case_sensitive;
+    let ops = &options.content;
     let mut errors = vec![];
-    //TODO: there is a fixed_strings in RegexMatcherBuilder, but it is not updated on
-    //crates.io yet, so we manually escape
-    let mut pattern = pattern.to_string();
-    if ops.nonregex {
-        pattern = regex::escape(&pattern);
-    }
-    ////
-
-    let matcher = RegexMatcherBuilder::new().case_insensitive(case_insensitive).build(&pattern);
-
-    if matcher.is_err() {
-        return ContentResults::default();
+    let mut matchers = vec![];
+    for term in terms {
+        let query = IndexQuery::new(&term.pattern, ops);
+        let matcher = match content_matcher(&term.pattern, ops) {
+            Ok(matcher) => matcher,
+            Err(err) => {
+                return ContentResults {
+                    results: vec![],
+                    errors: vec![SearchError::InvalidRegex {
+                        pattern: term.pattern.clone(),
+                        source: Arc::new(err),
+                    }],
+                };
+            }
+        };
+        matchers.push(TermMatcher {
+            matcher,
+            occur: term.occur,
+            query,
+        });
     }
-    let matcher = matcher.unwrap();
-
-    let mut searcher = SearcherBuilder::new()
-        .binary_detection(BinaryDetection::quit(b'\x00'))
-        .line_number(true)
-        .build();
-
-    let my_write = MyWrite { data: vec![] };
-    let mut printer = StandardBuilder::new()
-        .separator_field_match(SEPARATOR.as_bytes().to_vec())
-        .build_no_color(my_write);
+    let filter = match MetadataFilter::new(&options.name) {
+        Ok(filter) => filter,
+        Err(message) => {
+            return ContentResults {
+                results: vec![],
+                errors: vec![SearchError::InvalidOptions { message }],
+            };
+        }
+    };
 
-    if allowed_files.len() > 0 {
+    if !allowed_files.is_empty() {
+        let mut searcher = build_searcher(ops);
+        let mut results = vec![];
         for path in allowed_files {
             if global_search_id.load(Ordering::Relaxed) != start_search_id {
                 return ContentResults::default();
             }
-            read_file(
-                &Path::new(&path),
+            if !filter.is_empty() && !std::fs::metadata(path).is_ok_and(|m| filter.matches(&m)) {
+                continue;
+            }
+            let root = Search::root_of(Path::new(path), paths)
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default();
+            for mut fi in read_file(
+                Path::new(&path),
                 &mut searcher,
-                &matcher,
-                &mut printer,
+                &matchers,
                 &mut errors,
-                &ops,
+                ops,
                 total_search_count.clone(),
-            );
+                index,
+            ) {
+                fi.root = root.clone();
+                match file_found {
+                    Some(file_found) => file_found(fi),
+                    None => results.push(fi),
+                }
+            }
         }
-    } else {
-        for path in paths {
-            for result in WalkDir::new(path) {
+        return ContentResults { results, errors };
+    }
+
+    let results = Arc::new(Mutex::new(vec![]));
+    let errors = Arc::new(Mutex::new(errors));
+    //each root is walked on its own, so that globs are relative to it
+    for root in paths {
+        let walker = match walk_builder(std::slice::from_ref(root), &options.name) {
+            Ok(builder) => builder.build_parallel(),
+            Err(message) => {
+                return ContentResults {
+                    results: vec![],
+                    errors: vec![SearchError::InvalidOptions { message }],
+                };
+            }
+        };
+        let root = root.to_string_lossy().to_string();
+        walker.run(|| {
+            //each thread gets its own searcher and matcher
+            let mut searcher = build_searcher(ops);
+            let matchers = matchers.clone();
+            let results = results.clone();
+            let errors = errors.clone();
+            let global_search_id = global_search_id.clone();
+            let total_search_count = total_search_count.clone();
+            let ops = ops.clone();
+            let filter = &filter;
+            let root = &root;
+            Box::new(move |result| {
                 if global_search_id.load(Ordering::Relaxed) != start_search_id {
-                    return ContentResults::default();
+                    return WalkState::Quit;
                 }
-
                 let dent = match result {
                     Ok(dent) => dent,
-                    Err(_err) => {
-                        errors.push(format!("Could not read file {path:?}"));
-                        continue;
+                    Err(err) => {
+                        errors.lock().unwrap().push(SearchError::walk(err));
+                        return WalkState::Continue;
                     }
                 };
-
-                if !dent.file_type().is_file() {
-                    continue;
+                if !dent.file_type().is_some_and(|ft| ft.is_file()) {
+                    return WalkState::Continue;
                 }
-                read_file(
-                    &dent.path(),
+                if !filter.is_empty() && !dent.metadata().is_ok_and(|m| filter.matches(&m)) {
+                    return WalkState::Continue;
+                }
+                let mut file_errors = vec![];
+                for mut fi in read_file(
+                    dent.path(),
                     &mut searcher,
-                    &matcher,
-                    &mut printer,
-                    &mut errors,
+                    &matchers,
+                    &mut file_errors,
                     &ops,
                     total_search_count.clone(),
-                );
-            }
+                    index,
+                ) {
+                    fi.root = root.clone();
+                    match file_found {
+                        Some(file_found) => file_found(fi),
+                        None => results.lock().unwrap().push(fi),
+                    }
+                }
+                if !file_errors.is_empty() {
+                    errors.lock().unwrap().extend(file_errors);
+                }
+                WalkState::Continue
+            })
+        });
+    }
+
+    if global_search_id.load(Ordering::Relaxed) != start_search_id {
+        return ContentResults::default();
+    }
+    ContentResults {
+        results: std::mem::take(&mut *results.lock().unwrap()),
+        errors: std::mem::take(&mut *errors.lock().unwrap()),
+    }
+}
+
+///walker over the given paths, configured with the name options, so that name and content searches skip the same files.
+///Fails if a glob or file type of the options is invalid
+pub fn walk_builder(paths: &[OsString], ops: &NameOptions) -> Result<WalkBuilder, String> {
+    let root = paths.first().cloned().unwrap_or_else(|| OsString::from("."));
+    let (overrides, types) = overrides_and_types(Path::new(&root), ops)?;
+    let mut builder = WalkBuilder::new(root);
+    for path in paths.iter().skip(1) {
+        builder.add(path);
+    }
+    builder
+        .follow_links(ops.follow_links)
+        .same_file_system(ops.same_filesystem)
+        .threads(num_cpus::get())
+        .hidden(ops.ignore_dot)
+        .git_ignore(ops.use_gitignore)
+        .overrides(overrides)
+        .types(types)
+        .max_depth(ops.max_depth)
+        .min_depth(ops.min_depth);
+    Ok(builder)
+}
+
+///the globs (relative to root) and file types of the name options, like ripgrep's `-g` and `-t`.
+///Excluded directories are globs that only match directories
+pub fn overrides_and_types(root: &Path, ops: &NameOptions) -> Result<(Override, Types), String> {
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in ops.globs.iter() {
+        overrides.add(glob).map_err(|e| format!("Invalid glob {glob}: {e}"))?;
+    }
+    for dir in ops.exclude_dirs.iter() {
+        let glob = format!("!{}/", dir.trim_end_matches('/'));
+        overrides.add(&glob).map_err(|e| format!("Invalid exclude {dir}: {e}"))?;
+    }
+    let overrides = overrides.build().map_err(|e| e.to_string())?;
+
+    let mut types = TypesBuilder::new();
+    types.add_defaults();
+    for def in ops.type_defs.iter() {
+        types.add_def(def).map_err(|e| format!("Invalid type definition {def}: {e}"))?;
+    }
+    for name in ops.types.iter() {
+        match name.strip_prefix('!') {
+            Some(name) => types.negate(name),
+            None => types.select(name),
+        };
+    }
+    let types = types.build().map_err(|e| e.to_string())?;
+    Ok((overrides, types))
+}
+
+///names and globs of the known file types, including the ones defined in the options
+pub fn type_definitions(ops: &NameOptions) -> Vec<(String, Vec<String>)> {
+    let mut types = TypesBuilder::new();
+    types.add_defaults();
+    for def in ops.type_defs.iter() {
+        let _ = types.add_def(def);
+    }
+    types
+        .definitions()
+        .into_iter()
+        .map(|d| (d.name().to_string(), d.globs().to_vec()))
+        .collect()
+}
+
+///whether the walker would skip `path` under `root`, because of depth, globs, types, hidden or ignore files.
+///For paths that are not found by walking, e.g. when watching
+pub fn is_ignored(root: &Path, path: &Path, is_dir: bool, ops: &NameOptions) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return true;
+    };
+    let Ok((overrides, types)) = overrides_and_types(root, ops) else {
+        return true;
+    };
+    let count = relative.components().count();
+    if ops.max_depth.is_some_and(|max| count > max) || ops.min_depth.is_some_and(|min| count < min) {
+        return true;
+    }
+    //the walker does not go into skipped folders, so each folder down to the path is checked
+    let mut current = root.to_path_buf();
+    for (i, component) in relative.components().enumerate() {
+        current.push(component);
+        let is_dir = i + 1 < count || is_dir;
+        //same order as the walker: globs first, then ignore files and types, and hidden unless whitelisted
+        match overrides.matched(&current, is_dir) {
+            m if m.is_ignore() => return true,
+            m if m.is_whitelist() => continue,
+            _ => (),
+        }
+        let mut whitelisted = false;
+        match ignore_file_match(&current, is_dir, ops) {
+            m if m.is_ignore() => return true,
+            m if m.is_whitelist() => whitelisted = true,
+            _ => (),
+        }
+        match types.matched(&current, is_dir) {
+            m if m.is_ignore() => return true,
+            m if m.is_whitelist() => whitelisted = true,
+            _ => (),
+        }
+        if ops.ignore_dot && !whitelisted && component.as_os_str().to_string_lossy().starts_with('.') {
+            return true;
+        }
+    }
+    false
+}
+
+///read like the walker (see `walk_builder`) reads them: `.ignore` files always, before `.gitignore` files, the git excludes
+///and the global gitignore, which are only read with `use_gitignore` inside a git repository.
+///Ignore files above the root count too, and closer ones take precedence
+fn ignore_file_match(path: &Path, is_dir: bool, ops: &NameOptions) -> ignore::Match<()> {
+    let dirs: Vec<&Path> = path.ancestors().skip(1).filter(|d| !d.as_os_str().is_empty()).collect();
+    let mut ignores: Vec<Gitignore> = dirs.iter().map(|dir| ignore_file(dir, &dir.join(".ignore"))).collect();
+    if ops.use_gitignore
+        && let Some(repo) = dirs.iter().find(|dir| dir.join(".git").exists())
+    {
+        ignores.extend(dirs.iter().map(|dir| ignore_file(dir, &dir.join(".gitignore"))));
+        ignores.push(ignore_file(repo, &repo.join(".git/info/exclude")));
+        ignores.push(Gitignore::global().0);
+    }
+    for ignore in ignores {
+        match ignore.matched(path, is_dir) {
+            m if m.is_none() => (),
+            m => return m.map(|_| ()),
         }
     }
-    let strings: Vec<String> = printer.into_inner().into_inner().string().split('\n').map(|x| x.to_string()).collect();
+    ignore::Match::None
+}
 
-    ContentResults { results: strings, errors }
+fn ignore_file(dir: &Path, file: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    //missing files are not an error here
+    builder.add(file);
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
 }
+
+///the matcher of a content pattern, with the case, multiline and literal options
+pub fn content_matcher(pattern: &str, ops: &ContentOptions) -> Result<RegexMatcher, grep::regex::Error> {
+    //TODO: there is a fixed_strings in RegexMatcherBuilder, but it is not updated on
+    //crates.io yet, so we manually escape
+    let mut pattern = pattern.to_string();
+    if ops.nonregex {
+        pattern = regex::escape(&pattern);
+    }
+    ////
+
+    RegexMatcherBuilder::new()
+        .case_insensitive(!ops.case_sensitive)
+        .multi_line(ops.multiline)
+        .build(&pattern)
+}
+
+fn build_searcher(ops: &ContentOptions) -> Searcher {
+    SearcherBuilder::new()
+        .binary_detection(BinaryDetection::quit(b'\x00'))
+        .line_number(true)
+        .multi_line(ops.multiline)
+        .before_context(ops.before_context)
+        .after_context(ops.after_context)
+        .build()
+}
+
+///searches a single file for each term, and returns it if it matches the terms.
+///Entries of archives that match are returned as their own files.
+///With an index, files that cannot match are not read or extracted, and small extracted text is taken from the index
 fn read_file(
     path: &Path,
     searcher: &mut Searcher,
-    matcher: &RegexMatcher,
-    printer: &mut Standard<NoColor<MyWrite>>,
-    errors: &mut Vec<String>,
+    matchers: &[TermMatcher],
+    errors: &mut Vec<SearchError>,
     ops: &ContentOptions,
     total_search_count: Option<Arc<AtomicUsize>>,
-) {
+    index: Option<&ContentIndex>,
+) -> Vec<FileInfo> {
     if let Some(total_search_count) = total_search_count.as_ref() {
         total_search_count.fetch_add(1, Ordering::Relaxed);
     }
-    let file = File::open(path);
-    match file {
-        Ok(file) => {
-            //normal grep
-            let result = searcher.search_file(matcher, &file, printer.sink_with_path(matcher, &path));
-            if let Err(_err) = result {
-                errors.push(format!("Could not read file {path:?}"));
+    let mut files = vec![];
+    //matches of each term
+    let mut matches = vec![vec![]; matchers.len()];
+    let mut plugin = None;
+
+    let key = index.and_then(|_| FileKey::new(path));
+    let mut entry = index.zip(key.as_ref()).and_then(|(index, key)| index.get(path, key));
+    let mut entry_changed = false;
+    //binary data stops the search of each term, but is only reported once
+    let mut binary = false;
+
+    let mut extendeds = vec![];
+    if ops.extended {
+        let extension = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+        extendeds = extended::extractors_for(&extension);
+        for ext in ops.extractors.iter().filter(|a| a.extensions().contains(&extension)) {
+            extendeds.push(Arc::new(ext.clone()));
+        }
+    }
+
+    //normal grep, except of files an extractor reads, whose bytes are not text
+    match (&entry, &key) {
+        _ if !extendeds.is_empty() => {
+            if entry.is_none()
+                && let Some(key) = &key
+            {
+                entry = Some(IndexEntry::new(key, None));
+                entry_changed = true;
+            }
+        }
+        (Some(entry), _) if !may_match(|q| entry.may_match(q), matchers) => {}
+        //not indexed yet, so read it whole to index it
+        (None, Some(key)) if key.size() <= MAX_INDEXED_SIZE => match std::fs::read(path) {
+            Ok(data) => {
+                for (i, term) in matchers.iter().enumerate() {
+                    let mut sink = MatchSink::new(&term.matcher, i, &mut matches[i], ops);
+                    if let Err(err) = searcher.search_slice(&term.matcher, &data, &mut sink) {
+                        errors.push(SearchError::io(path, err));
+                    }
+                    binary |= sink.binary;
+                }
+                entry = Some(IndexEntry::new(key, Some(&data)));
+                entry_changed = true;
+            }
+            Err(err) => {
+                errors.push(SearchError::io(path, err));
+                return files;
             }
+        },
+        _ => match File::open(path) {
+            Ok(mut file) => {
+                for (i, term) in matchers.iter().enumerate() {
+                    //each term reads the file from the start
+                    let mut sink = MatchSink::new(&term.matcher, i, &mut matches[i], ops);
+                    let result = file.rewind().and_then(|_| searcher.search_file(&term.matcher, &file, &mut sink));
+                    if let Err(err) = result {
+                        errors.push(SearchError::io(path, err));
+                    }
+                    binary |= sink.binary;
+                }
+                if entry.is_none()
+                    && let Some(key) = &key
+                {
+                    entry = Some(IndexEntry::new(key, None));
+                    entry_changed = true;
+                }
+            }
+            Err(err) => {
+                errors.push(SearchError::io(path, err));
+                return files;
+            }
+        },
+    }
+    if binary {
+        errors.push(SearchError::Binary {
+            path: path.to_string_lossy().to_string(),
+        });
+    }
 
-            //apply each of extensions
-            if ops.extended {
-                let extension = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
-                let extendeds = vec![ExtendedType::Pdf, ExtendedType::Office];
-                for ext in extendeds.iter().filter(|a| a.extensions().contains(&extension)) {
-                    if let Ok(data) = ext.to_string(&path) {
-                        let cursor = Cursor::new(data);
-                        let result = searcher.search_reader(
-                            matcher,
-                            cursor,
-                            printer.sink_with_path(matcher, &format!("{}{}{}", path.to_string_lossy(), EXTENSION_SEPARATOR, ext.name())),
-                        );
-                        if let Err(_err) = result {
-                            errors.push(format!("Could not read file {path:?} with extension {ext:?}"));
-                        }
-                    } else {
+    //apply each of extensions
+    if !extendeds.is_empty() {
+        let names: Vec<String> = extendeds.iter().map(|ext| ext.name()).collect();
+        let cached = match &entry {
+            Some(IndexEntry {
+                extracted: Some(extracted), ..
+            }) if extracted.extractors == names => Some(extracted),
+            _ => None,
+        };
+        let extracted = match cached {
+            //its text cannot match, so it is not extracted again
+            Some(cached) if !may_match(|q| cached.may_match(q), matchers) => vec![],
+            Some(Extracted { entries: Some(entries), .. }) => entries.clone(),
+            _ => {
+                let mut extracted = vec![];
+                for ext in extendeds.iter() {
+                    match ext.to_entries(path) {
+                        Ok(entries) => extracted.extend(entries.into_iter().map(|(entry, data)| (ext.name(), entry, data))),
+                        Err(err) => errors.push(SearchError::ExtractorFailed {
+                            path: path.to_string_lossy().to_string(),
+                            extractor: ext.name(),
+                            source: Arc::from(err),
+                        }),
                     }
                 }
+                if let Some(entry) = entry.as_mut() {
+                    entry.extracted = Some(Extracted::new(names, &extracted));
+                    entry_changed = true;
+                }
+                extracted
+            }
+        };
+        for (name, entry, data) in extracted {
+            let mut entry_matches = vec![vec![]; matchers.len()];
+            for (i, term) in matchers.iter().enumerate() {
+                let result = searcher.search_slice(
+                    &term.matcher,
+                    data.as_bytes(),
+                    MatchSink::new(&term.matcher, i, &mut entry_matches[i], ops),
+                );
+                if let Err(err) = result {
+                    errors.push(SearchError::ExtractorFailed {
+                        path: path.to_string_lossy().to_string(),
+                        extractor: name.clone(),
+                        source: Arc::new(err),
+                    });
+                }
+            }
+            if entry_matches.iter().all(|x| x.is_empty()) {
+                continue;
+            }
+            if entry.is_empty() {
+                for (all, found) in matches.iter_mut().zip(entry_matches) {
+                    all.extend(found);
+                }
+                plugin = Some(name);
+            } else if let Some(entry_matches) = matching_terms(matchers, entry_matches) {
+                let entry_path = format!("{}{}{}", path.to_string_lossy(), extended::ENTRY_SEPARATOR, entry);
+                files.push(new_fileinfo(Path::new(&entry_path), entry_matches, Some(name)));
             }
         }
-        Err(_err) => {
-            errors.push(format!("Could not read {}", path.as_os_str().to_string_lossy()));
+    }
+
+    if entry_changed && let (Some(index), Some(entry)) = (index, entry) {
+        index.insert(path, entry);
+    }
+    if let Some(matches) = matching_terms(matchers, matches) {
+        files.insert(0, new_fileinfo(path, matches, plugin));
+    }
+    files
+}
+
+///the matches of all terms in line order, if they match the terms
+fn matching_terms(matchers: &[TermMatcher], matches: Vec<Vec<Match>>) -> Option<Vec<Match>> {
+    let found = matchers.iter().zip(&matches).map(|(t, m)| (t.occur, m.iter().any(|x| !x.is_context)));
+    if !search::terms_match(found) {
+        return None;
+    }
+    let mut matches: Vec<Match> = matches.into_iter().flatten().collect();
+    matches.sort_by_key(|x| x.line);
+    Some(matches)
+}
+
+///false if the index shows the file cannot match the terms. Files are never skipped for containing a `MustNot` term
+fn may_match(filter: impl Fn(&IndexQuery) -> bool, matchers: &[TermMatcher]) -> bool {
+    search::terms_match(matchers.iter().map(|t| (t.occur, t.occur != Occur::MustNot && filter(&t.query))))
+}
+
+fn new_fileinfo(path: &Path, matches: Vec<Match>, plugin: Option<String>) -> FileInfo {
+    let (size, modified) = FileInfo::metadata(path); //None for entries of archives
+    FileInfo {
+        path: path.to_string_lossy().to_string(),
+        root: String::new(), //set by the caller, which knows the root
+        matches,
+        ext: path.extension().unwrap_or_default().to_string_lossy().to_string(),
+        name: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        is_folder: false,
+        plugin,
+        ranges: vec![],
+        score: None,
+        size,
+        modified,
+    }
+}
+
+///Collects each matching line of a file as a `Match`, along with the ranges of the match within the line.
+///Context lines are collected too, marked with `is_context`
+struct MatchSink<'a> {
+    matcher: &'a RegexMatcher,
+    term: usize,
+    matches: &'a mut Vec<Match>,
+    binary: bool, //the search stopped at binary data
+    max_count: Option<usize>,
+    max_line_len: Option<usize>,
+    count: usize, //matching lines found
+}
+
+impl<'a> MatchSink<'a> {
+    fn new(matcher: &'a RegexMatcher, term: usize, matches: &'a mut Vec<Match>, ops: &ContentOptions) -> Self {
+        Self {
+            matcher,
+            term,
+            matches,
+            binary: false,
+            max_count: ops.max_count,
+            max_line_len: ops.max_line_len,
+            count: 0,
         }
     }
 }
-impl MyWrite {
-    pub fn string(&self) -> String {
-        String::from_utf8_lossy(&self.data).to_string()
+
+impl Sink for MatchSink<'_> {
+    type Error = std::io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, Self::Error> {
+        let mut content = line_to_string(mat.bytes());
+        //find ranges on the (lossy) string, so they are valid char boundaries
+        let mut ranges = vec![];
+        self.matcher
+            .find_iter(content.as_bytes(), |m| {
+                ranges.push(m.start()..m.end());
+                true
+            })
+            .map_err(std::io::Error::other)?;
+        if let Some(cut) = cut_at(&content, self.max_line_len) {
+            content.truncate(cut);
+            ranges.retain(|x| x.start < cut);
+            ranges.iter_mut().for_each(|x| x.end = x.end.min(cut));
+        }
+
+        let line = mat.line_number().unwrap_or_default() as usize;
+        self.matches.push(Match {
+            line,
+            end_line: line + mat.lines().count().saturating_sub(1),
+            offset: mat.absolute_byte_offset(),
+            content,
+            ranges,
+            is_context: false,
+            term: self.term,
+        });
+        //like rg -m, the search of the file stops at the max
+        self.count += 1;
+        Ok(self.max_count.is_none_or(|max| self.count < max))
     }
+
+    fn context(&mut self, _searcher: &Searcher, context: &SinkContext<'_>) -> Result<bool, Self::Error> {
+        let line = context.line_number().unwrap_or_default() as usize;
+        let mut content = line_to_string(context.bytes());
+        if let Some(cut) = cut_at(&content, self.max_line_len) {
+            content.truncate(cut);
+        }
+        self.matches.push(Match {
+            line,
+            end_line: line,
+            offset: context.absolute_byte_offset(),
+            content,
+            ranges: vec![],
+            is_context: true,
+            term: self.term,
+        });
+        Ok(true)
+    }
+
+    fn binary_data(&mut self, _searcher: &Searcher, _binary_byte_offset: u64) -> Result<bool, Self::Error> {
+        self.binary = true;
+        Ok(true)
+    }
+}
+
+///byte index of the char at max_len, None if the text is not longer
+fn cut_at(text: &str, max_len: Option<usize>) -> Option<usize> {
+    text.char_indices().nth(max_len?).map(|(i, _)| i)
 }
 
-impl Write for MyWrite {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.data.extend_from_slice(buf);
-        Ok(buf.len())
+fn line_to_string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end_matches(['\n', '\r']).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn globs_and_types() {
+        let mut root = std::env::temp_dir();
+        root.push("rusltestdirglobs");
+        let _ = std::fs::remove_dir_all(&root);
+        for file in ["src/main.rs", "src/notes.txt", "target/debug/build.rs", "web/index.html"] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let ops = NameOptions {
+            globs: vec!["!target".to_string()],
+            types: vec!["rust".to_string(), "web".to_string()],
+            type_defs: vec!["web:*.html".to_string()],
+            ..Default::default()
+        };
+        let mut found: Vec<String> = walk_builder(&[root.clone().into_os_string()], &ops)
+            .unwrap()
+            .build()
+            .filter_map(|d| d.ok())
+            .filter(|d| d.file_type().is_some_and(|t| t.is_file()))
+            .map(|d| d.path().strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        found.sort();
+        assert_eq!(found, vec!["src/main.rs", "web/index.html"]);
+
+        //watched paths are skipped the same way
+        assert!(!is_ignored(&root, &root.join("src/main.rs"), false, &ops));
+        assert!(is_ignored(&root, &root.join("src/notes.txt"), false, &ops));
+        assert!(is_ignored(&root, &root.join("target/debug/build.rs"), false, &ops));
+
+        //excluded folders and depths
+        let ops = NameOptions {
+            exclude_dirs: vec!["debug".to_string()],
+            max_depth: Some(2),
+            min_depth: Some(2),
+            ..Default::default()
+        };
+        let mut found: Vec<String> = walk_builder(&[root.clone().into_os_string()], &ops)
+            .unwrap()
+            .build()
+            .filter_map(|d| d.ok())
+            .map(|d| d.path().strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        found.sort();
+        assert_eq!(found, vec!["src/main.rs", "src/notes.txt", "web/index.html"]);
+        assert!(is_ignored(&root, &root.join("target/debug"), true, &ops));
+        assert!(is_ignored(&root, &root.join("src"), true, &ops));
+
+        let ops = NameOptions {
+            types: vec!["nosuchtype".to_string()],
+            ..Default::default()
+        };
+        assert!(walk_builder(&[root.into_os_string()], &ops).is_err());
+    }
+    #[test]
+    fn ignore_files() {
+        let mut root = std::env::temp_dir();
+        root.push("rusltestdirignore");
+        let _ = std::fs::remove_dir_all(&root);
+        for file in ["src/main.rs", "src/notes.txt", "web/index.html"] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "notes.txt\n").unwrap();
+        std::fs::write(root.join(".ignore"), "web/\n").unwrap();
+        //the same as the walker finds
+        let found = |ops: &NameOptions| {
+            let mut found: Vec<String> = walk_builder(&[root.clone().into_os_string()], ops)
+                .unwrap()
+                .build()
+                .filter_map(|d| d.ok())
+                .filter(|d| d.file_type().is_some_and(|t| t.is_file()))
+                .map(|d| d.path().strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            found.sort();
+            let ignored: Vec<&str> = ["src/main.rs", "src/notes.txt", "web/index.html"]
+                .into_iter()
+                .filter(|x| is_ignored(&root, &root.join(x), false, ops))
+                .collect();
+            (found, ignored)
+        };
+        //.gitignore is only read in a git repository, .ignore always
+        let ops = NameOptions::default();
+        assert_eq!(
+            found(&ops),
+            (vec!["src/main.rs".to_string(), "src/notes.txt".to_string()], vec!["web/index.html"])
+        );
+        let ops = NameOptions {
+            use_gitignore: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            found(&ops),
+            (vec!["src/main.rs".to_string(), "src/notes.txt".to_string()], vec!["web/index.html"])
+        );
+        std::fs::create_dir(root.join(".git")).unwrap();
+        let ops = NameOptions::default();
+        assert_eq!(found(&ops), (vec!["src/main.rs".to_string()], vec!["src/notes.txt", "web/index.html"]));
     }
+    #[test]
+    fn errors() {
+        let mut root = std::env::temp_dir();
+        root.push("rusltestdirerrors");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("data.bin"), b"41\n\x00\x0141").unwrap();
+        let search = |pattern: &str| {
+            search_contents(
+                &[ContentTerm::new(pattern, Occur::Must)],
+                &[root.clone().into_os_string()],
+                &HashSet::new(),
+                &Options::default(),
+                Arc::new(AtomicUsize::new(0)),
+                0,
+                None,
+                None,
+                None,
+            )
+        };
+        let found = search("41");
+        assert_eq!(found.results.len(), 1);
+        let kinds: Vec<ErrorKind> = found.errors.iter().map(|x| x.kind()).collect();
+        assert_eq!(kinds, vec![ErrorKind::Binary]);
 
-    fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+        let found = search("4(1");
+        assert!(found.results.is_empty());
+        assert!(matches!(&found.errors[..], [SearchError::InvalidRegex { pattern, .. }] if pattern == "4(1"));
     }
 }
@@ -1,15 +1,119 @@
-#[derive(Clone, Debug)]
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+///separates dirs typed in one text box, like the PATH variable
+pub const DIR_SEPARATOR: char = if cfg!(windows) { ';' } else { ':' };
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Search {
-    pub dir: String,
+    pub dirs: Vec<String>, //roots to search, the results of all are merged
     pub name_text: String,
     pub contents_text: String,
+    pub terms: Vec<ContentTerm>, //more content patterns, which a file must, may or must not contain
 }
 impl Default for Search {
     fn default() -> Self {
         Self {
-            dir: ".".to_string(),
+            dirs: vec![".".to_string()],
             name_text: String::new(),
             contents_text: String::new(),
+            terms: vec![],
         }
     }
 }
+
+///A content pattern, and how it decides if a file matches
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentTerm {
+    pub pattern: String,
+    pub occur: Occur,
+}
+
+///A file matches if it contains all `Must` terms, at least one of the `Should` terms (if there are any), and none of the `MustNot` terms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Occur {
+    Must,
+    Should,
+    MustNot,
+}
+
+impl ContentTerm {
+    pub fn new(pattern: &str, occur: Occur) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            occur,
+        }
+    }
+}
+
+///true if a file matches the terms, given how each term occurs and if it was found in the file
+pub fn terms_match(found: impl IntoIterator<Item = (Occur, bool)>) -> bool {
+    let mut should = None;
+    for (occur, found) in found {
+        match occur {
+            Occur::Must if !found => return false,
+            Occur::MustNot if found => return false,
+            Occur::Should => should = Some(should.unwrap_or(false) || found),
+            _ => {}
+        }
+    }
+    should.unwrap_or(true)
+}
+
+impl Search {
+    ///`contents_text` (if any) as a `Must` term, followed by the other terms. `Match::term` is an index into these
+    pub fn content_terms(&self) -> Vec<ContentTerm> {
+        let first = (!self.contents_text.is_empty()).then(|| ContentTerm::new(&self.contents_text, Occur::Must));
+        first
+            .into_iter()
+            .chain(self.terms.iter().filter(|x| !x.pattern.is_empty()).cloned())
+            .collect()
+    }
+
+    ///true if there is a content search
+    pub fn has_contents(&self) -> bool {
+        !self.content_terms().is_empty()
+    }
+
+    ///dirs separated by `DIR_SEPARATOR`, e.g. `/etc:/home/me/.config`
+    pub fn split_dirs(text: &str) -> Vec<String> {
+        text.split(DIR_SEPARATOR)
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect()
+    }
+
+    pub fn join_dirs(dirs: &[String]) -> String {
+        dirs.join(&DIR_SEPARATOR.to_string())
+    }
+
+    ///dirs to walk, without duplicates and dirs inside another dir, which would be searched twice
+    pub fn roots(&self) -> Vec<String> {
+        let dirs: Vec<(&String, PathBuf)> = self
+            .dirs
+            .iter()
+            .filter(|x| !x.is_empty())
+            .map(|x| (x, std::fs::canonicalize(x).unwrap_or_else(|_| PathBuf::from(x))))
+            .collect();
+        dirs.iter()
+            .enumerate()
+            .filter(|(i, (_, path))| {
+                !dirs
+                    .iter()
+                    .enumerate()
+                    .any(|(j, (_, other))| (path != other && path.starts_with(other)) || (path == other && j < *i))
+            })
+            .map(|(_, (dir, _))| dir.to_string())
+            .collect()
+    }
+
+    ///the root a path was found under, the deepest if roots are nested
+    pub fn root_of<'a, P: AsRef<Path>>(path: &Path, roots: &'a [P]) -> Option<&'a P> {
+        roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.as_ref().components().count())
+    }
+}
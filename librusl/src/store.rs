@@ -0,0 +1,100 @@
+use std::{
+    ops::Range,
+    sync::{Arc, RwLock},
+};
+
+use crate::{fileinfo::FileInfo, manager::Manager, options::Sort};
+
+///Results of the last search, kept by the `Manager` as they are found, and shared with the GUIs.
+///A page or a range of them can be read without cloning the others, see `Manager::results`
+#[derive(Debug, Default)]
+pub struct ResultStore {
+    pub(crate) files: Vec<FileInfo>,
+    pub(crate) id: usize,
+    pub(crate) done: bool,
+    pub(crate) changes: usize,
+}
+
+pub type SharedResults = Arc<RwLock<ResultStore>>;
+
+impl ResultStore {
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    ///id of the search the results are of, the same as `FinalResults::id`
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    ///false while the search is running, so more results may come
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    ///counts every change to the results, so a copy of some of them can tell it is stale
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    pub fn get(&self, index: usize) -> Option<&FileInfo> {
+        self.files.get(index)
+    }
+
+    ///the results in range, cut to the results there are
+    pub fn range(&self, range: Range<usize>) -> &[FileInfo] {
+        let end = range.end.min(self.files.len());
+        &self.files[range.start.min(end)..end]
+    }
+
+    ///page (from 0) of page_size results
+    pub fn page(&self, page: usize, page_size: usize) -> &[FileInfo] {
+        let start = page.saturating_mul(page_size);
+        self.range(start..start.saturating_add(page_size))
+    }
+
+    pub fn page_count(&self, page_size: usize) -> usize {
+        self.files.len().div_ceil(page_size.max(1))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, FileInfo> {
+        self.files.iter()
+    }
+
+    ///sorts the results in place, later pages are read in this order. See `Manager::do_sort`
+    pub fn sort(&mut self, sort: Sort, then: Sort, descending: bool) {
+        Manager::do_sort(&mut self.files, sort, then, descending);
+        self.changes += 1;
+    }
+
+    pub(crate) fn start(&mut self, id: usize) {
+        self.files.clear();
+        self.id = id;
+        self.done = false;
+        self.changes += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages() {
+        let files = ["c", "a", "b"].iter().map(|x| FileInfo::fixture(x, "")).collect();
+        let mut store = ResultStore { files, ..Default::default() };
+        assert_eq!(store.page_count(2), 2);
+        assert_eq!(store.page(1, 2).len(), 1);
+        assert!(store.page(5, 2).is_empty());
+        assert_eq!(store.range(1..10).len(), 2);
+
+        store.sort(Sort::Name, Sort::None, false);
+        assert_eq!(store.changes(), 1);
+        let names: Vec<&str> = store.page(0, 2).iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+}
@@ -1,9 +1,14 @@
 //hide windows console
 #![windows_subsystem = "windows"]
 
-use librusl::manager::{Manager, SearchResult};
-use librusl::options::{FTypes, Sort};
-use librusl::search::Search;
+use librusl::error::{ErrorKind, PatternError, PatternField, SearchError};
+use librusl::export;
+use librusl::manager::{FinalResults, Manager, SearchResult};
+use librusl::open::OpenTarget;
+use librusl::options::{FTypes, Options, SavedSearch, Sort};
+use librusl::search::{ContentTerm, Search};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc;
 use std::time::Instant;
 use std::vec;
@@ -14,7 +19,7 @@ use std::{
     time::Duration,
 };
 
-use slint::{ModelRc, VecModel, Weak};
+use slint::{ModelRc, SharedString, VecModel, Weak};
 
 use librusl::fileinfo::FileInfo;
 slint::include_modules!();
@@ -26,6 +31,14 @@ pub fn main() {
 
     //results
     let results: Arc<Mutex<Vec<FileInfo>>> = Arc::new(Mutex::new(vec![]));
+    //there are no boxes for terms, they come from a loaded saved search
+    let terms: Arc<Mutex<Vec<ContentTerm>>> = Arc::new(Mutex::new(vec![]));
+    //errors of the last search, shown by kind
+    let errors: Arc<Mutex<Vec<SearchError>>> = Arc::new(Mutex::new(vec![]));
+    //if results are kept live after the search, read from the window when searching
+    let watch = Arc::new(AtomicBool::new(false));
+    //see `Options::shown_results`, read from the options when searching
+    let shown = Arc::new(AtomicUsize::new(manager.lock().unwrap().get_options().shown_results()));
 
     //gui window
     let mw = MainWindow::new().unwrap();
@@ -36,6 +49,10 @@ pub fn main() {
     //spawn result receiver
     let weak_receiver = weak.clone();
     let results_receiver = results.clone();
+    let errors_receiver = errors.clone();
+    let manager_receiver = manager.clone();
+    let watch_receiver = watch.clone();
+    let shown_receiver = shown.clone();
     thread::spawn(move || {
         let weak = weak_receiver.clone();
         let mut current: Vec<FileInfo> = vec![];
@@ -47,9 +64,10 @@ pub fn main() {
                 break;
             }
             let rec = rec.unwrap();
+            let shown = shown_receiver.load(Ordering::Relaxed);
             match rec {
                 SearchResult::FinalResults(res) => {
-                    set_data(weak.clone(), res.data.iter().map(|x| x.to_owned()).collect(), res.duration, true);
+                    set_data(weak.clone(), res.data.iter().map(|x| x.to_owned()).collect(), shown, res.duration, true);
                     current.clear();
                     counter = 0;
                     let count = res.data.len();
@@ -57,18 +75,30 @@ pub fn main() {
                     let _ = weak.upgrade_in_event_loop(move |weak| {
                         weak.set_searching(false);
                         weak.set_find_button("Find".into());
-                        weak.set_message(format!("Found {count} in {:.3}s", res.duration.as_secs_f64()).into());
+                        let truncated = if res.truncated { " (max results reached)" } else { "" };
+                        weak.set_message(format!("Found {count} in {:.3}s{truncated}", res.duration.as_secs_f64()).into());
                     });
+                    let mut manager = manager_receiver.lock().unwrap();
+                    if watch_receiver.load(Ordering::Relaxed)
+                        && !manager.is_watching()
+                        && let Err(err) = manager.watch()
+                    {
+                        let mut errors = errors_receiver.lock().unwrap();
+                        errors.push(SearchError::other(None, err));
+                        let errors = errors.clone();
+                        let _ = weak.upgrade_in_event_loop(move |weak| show_errors(&weak, &errors));
+                    }
                 }
                 SearchResult::InterimResult(res) => {
                     counter += 1;
-                    if current.len() < 1000 {
+                    if current.len() < shown {
                         current.push(res);
 
                         if last_update.elapsed() > Duration::from_millis(100) {
                             set_data(
                                 weak.clone(),
                                 current.iter().map(|x| x.to_owned()).collect(),
+                                shown,
                                 Duration::from_secs(0),
                                 false,
                             );
@@ -78,8 +108,23 @@ pub fn main() {
 
                     let _ = weak.upgrade_in_event_loop(move |weak| weak.set_message(format!("Found {counter} ...").into()));
                 }
-                SearchResult::SearchErrors(_) => {}
+                SearchResult::SearchErrors(errs) => {
+                    let mut errors = errors_receiver.lock().unwrap();
+                    //like rg, binary files are skipped quietly
+                    errors.extend(errs.into_iter().filter(|x| x.kind() != ErrorKind::Binary));
+                    let errors = errors.clone();
+                    let _ = weak.upgrade_in_event_loop(move |weak| show_errors(&weak, &errors));
+                }
                 SearchResult::SearchCount(_) => {}
+                SearchResult::Watch(event) => {
+                    let files = {
+                        let mut results = results_receiver.lock().unwrap();
+                        event.apply(&mut results);
+                        results.clone()
+                    };
+                    set_data(weak.clone(), files, shown, Duration::from_secs(0), false);
+                    let _ = weak.upgrade_in_event_loop(move |weak| weak.set_message(format!("{event} (watching)").into()));
+                }
             };
         }
     });
@@ -87,19 +132,16 @@ pub fn main() {
     //on file search edited
     let weak_edited = weak.clone();
     let manager_search = manager.clone();
+    let terms_search = terms.clone();
+    let errors_search = errors.clone();
+    let watch_search = watch.clone();
+    let shown_search = shown.clone();
     mw.on_search(move || {
         let weak = weak_edited.clone().unwrap();
-        let name_text = weak.get_find_text().as_str().to_string();
-        let content_text = weak.get_content_find_text().as_str().to_string();
+        let search = get_search(&weak, &terms_search.lock().unwrap());
+        let (name_text, content_text) = (&search.name_text, &search.contents_text);
         let searching = weak.get_searching();
 
-        let dir = weak.get_directory().as_str().to_owned();
-        let search = Search {
-            name_text: name_text.to_string(),
-            contents_text: content_text.to_string(),
-            dir,
-        };
-
         get_and_update_options(manager_search.clone(), weak_edited.clone());
 
         let mut manager = manager_search.lock().unwrap();
@@ -109,7 +151,7 @@ pub fn main() {
             return;
         }
 
-        if !manager.dir_is_valid(&search.dir) {
+        if !manager.dirs_are_valid(&search.dirs) {
             weak.set_message("Invalid directory".into());
             return;
         }
@@ -126,7 +168,20 @@ pub fn main() {
             let model = VecModel::<SFileInfo>::from(vec![]);
             let modelrc = ModelRc::new(model);
             weak.set_files(modelrc);
-            manager.search(&search);
+            errors_search.lock().unwrap().clear();
+            weak.set_selected_errors(0);
+            show_errors(&weak, &[]);
+            watch_search.store(weak.get_watch(), Ordering::Relaxed);
+            shown_search.store(manager.get_options().shown_results(), Ordering::Relaxed);
+            if !weak.get_watch() {
+                manager.unwatch();
+            }
+            let result = manager.search(&search);
+            show_pattern_error(&weak, result.as_ref().err());
+            if result.is_err() {
+                weak.set_searching(false);
+                weak.set_find_button("Find".into());
+            }
         }
     });
 
@@ -141,31 +196,116 @@ pub fn main() {
             "Path" => Sort::Path,
             "Extension" => Sort::Extension,
             "Name" => Sort::Name,
+            "Relevance" => Sort::Relevance,
+            "Size" => Sort::Size,
+            "Modified" => Sort::Modified,
+            "Matches" => Sort::Matches,
+            "Depth" => Sort::Depth,
             "None" => Sort::None,
             _ => Sort::None,
         };
 
         let mut manager_sort = manager_sort.lock().unwrap();
         manager_sort.set_sort(sort_new);
+        let ops = manager_sort.get_options();
         let mut results_vec = results_sort.lock().unwrap().to_vec();
-        Manager::do_sort(&mut results_vec, sort_new);
+        Manager::do_sort(&mut results_vec, sort_new, ops.then_sort, ops.sort_descending);
         *results_sort.lock().unwrap() = results_vec.clone();
 
-        set_data(weak_sort.clone(), results_vec, Duration::from_secs(0), false);
+        set_data(weak_sort.clone(), results_vec, ops.shown_results(), Duration::from_secs(0), false);
     });
 
     //exports
     let results_export_paths = results.clone();
+    let weak_export = weak.clone();
     mw.on_export(move |typ: i32| {
         let results = results_export_paths.lock().unwrap();
         let _results: Vec<String> = match typ.into() {
             ExportType::FullPath => results.iter().map(|x| x.path.clone()).collect(),
             ExportType::Name => results.iter().map(|x| x.name.clone()).collect(),
+            ExportType::File => {
+                //copied, so the receiver is not blocked on the results while the dialog is open
+                let data = results.to_vec();
+                drop(results);
+                save_results(data, weak_export.clone());
+                return;
+            }
         };
 
         //manager_export.lock().unwrap().export(results);
     });
 
+    //saved searches
+    let weak_save = weak.clone();
+    let manager_save = manager.clone();
+    let terms_save = terms.clone();
+    mw.on_save_search(move || {
+        let weak = weak_save.unwrap();
+        let name = weak.get_saved_name().trim().to_string();
+        if name.is_empty() {
+            weak.set_message("Name the search to save it".into());
+            return;
+        }
+        get_and_update_options(manager_save.clone(), weak_save.clone());
+        let search = get_search(&weak, &terms_save.lock().unwrap());
+        let mut manager = manager_save.lock().unwrap();
+        let saved = SavedSearch::new(&name, &search, &manager.get_options());
+        manager.save_search(saved);
+        set_saved_names(&weak, &manager.get_options());
+        weak.set_selected_saved(name.clone().into());
+        weak.set_message(format!("Saved search {name}").into());
+    });
+
+    let weak_load = weak.clone();
+    let manager_load = manager.clone();
+    let terms_load = terms.clone();
+    mw.on_load_search(move || {
+        let weak = weak_load.unwrap();
+        let name = weak.get_selected_saved().to_string();
+        let mut manager = manager_load.lock().unwrap();
+        let Some(search) = manager.load_saved_search(&name) else {
+            return;
+        };
+        let mut ops = manager.get_options();
+        ops.last_dir = Search::join_dirs(&search.dirs);
+        manager.set_options(ops);
+        drop(manager);
+        set_options(weak_load.clone(), manager_load.clone());
+        weak.set_find_text(search.name_text.into());
+        weak.set_content_find_text(search.contents_text.into());
+        weak.set_saved_name(name.clone().into());
+        *terms_load.lock().unwrap() = search.terms;
+        weak.set_message(format!("Loaded search {name}").into());
+    });
+
+    let weak_remove = weak.clone();
+    let manager_remove = manager.clone();
+    mw.on_remove_search(move || {
+        let weak = weak_remove.unwrap();
+        let name = weak.get_selected_saved().to_string();
+        let mut manager = manager_remove.lock().unwrap();
+        manager.remove_saved_search(&name);
+        set_saved_names(&weak, &manager.get_options());
+        weak.set_selected_saved("".into());
+        weak.set_message(format!("Removed search {name}").into());
+    });
+
+    //open results
+    let weak_open = weak.clone();
+    let manager_open = manager.clone();
+    mw.on_open_file(move |path, line| {
+        //line 0 is the file rather than a match
+        let line = (line > 0).then_some(line as usize);
+        open_result(OpenTarget::File(path.to_string(), line), manager_open.clone(), weak_open.clone());
+    });
+    let weak_open = weak.clone();
+    let manager_open = manager.clone();
+    mw.on_open_folder(move |path| open_result(OpenTarget::Folder(path.to_string()), manager_open.clone(), weak_open.clone()));
+
+    //errors
+    let weak_errors = weak.clone();
+    mw.on_errors_changed(move || show_errors(&weak_errors.unwrap(), &errors.lock().unwrap()));
+
     //dirchange
     let weak_dir_changed = weak.clone();
     mw.on_dir_changed(move || {
@@ -184,10 +324,12 @@ pub fn main() {
     let weak_dir_clicked = weak;
     mw.on_dir_clicked(move || {
         let weak = weak_dir_clicked.clone().unwrap();
-        let dir = weak.get_directory().as_str().to_owned();
-        let new_dir = rfd::FileDialog::new().set_directory(dir).pick_folder();
-        if let Some(new_dir) = new_dir {
-            weak.set_directory(new_dir.to_str().unwrap_or(".").into());
+        let dir = Search::split_dirs(weak.get_directory().as_str()).into_iter().next().unwrap_or_default();
+        //several folders can be picked at once
+        let new_dirs = rfd::FileDialog::new().set_directory(dir).pick_folders();
+        if let Some(new_dirs) = new_dirs {
+            let dirs: Vec<String> = new_dirs.iter().map(|x| x.to_string_lossy().to_string()).collect();
+            weak.set_directory(Search::join_dirs(&dirs).into());
         }
     });
 
@@ -214,10 +356,20 @@ fn get_and_update_options(manager: Arc<Mutex<Manager>>, weak: Weak<MainWindow>)
 
     //get content options
     ops.content.case_sensitive = weak.get_content_case_sensitive();
+    ops.editor = weak.get_editor().to_string();
     man.set_options(ops);
 }
 
-fn set_data(weak: Weak<MainWindow>, files: Vec<FileInfo>, elapsed: Duration, finished: bool) {
+fn get_search(weak: &MainWindow, terms: &[ContentTerm]) -> Search {
+    Search {
+        name_text: weak.get_find_text().to_string(),
+        contents_text: weak.get_content_find_text().to_string(),
+        terms: terms.to_vec(),
+        dirs: Search::split_dirs(weak.get_directory().as_str()),
+    }
+}
+
+fn set_data(weak: Weak<MainWindow>, files: Vec<FileInfo>, shown: usize, elapsed: Duration, finished: bool) {
     let _ = weak.upgrade_in_event_loop(move |weak| {
         let count = files.len() as i32;
         let max_count = 100;
@@ -233,11 +385,12 @@ fn set_data(weak: Weak<MainWindow>, files: Vec<FileInfo>, elapsed: Duration, fin
 
         let mut sfiles: Vec<SFileInfo> = files
             .iter()
-            .take(1000)
+            .take(shown)
             .map(|x| {
+                let lines = x.unique_lines();
                 let mut content = x.content(max_count, max_len);
-                if x.matches.len() > max_count {
-                    content.push_str(&format!("\nand {} other lines", x.matches.len() - max_count));
+                if lines.len() > max_count {
+                    content.push_str(&format!("\nand {} other lines", lines.len() - max_count));
                 };
 
                 let pre = match (is_content_search, ops, x.is_folder) {
@@ -247,12 +400,12 @@ fn set_data(weak: Weak<MainWindow>, files: Vec<FileInfo>, elapsed: Duration, fin
                     (false, _, true) => "📁",
                     (false, _, false) => "📝",
                 };
-                let data = x
-                    .matches
+                let data = lines
                     .iter()
                     .map(|mat| MatchInfo {
                         content: FileInfo::limited_match(mat, max_len, false).into(),
                         line: format!("{}:", mat.line).into(),
+                        line_number: mat.line as i32,
                     })
                     .collect::<Vec<_>>();
 
@@ -263,11 +416,11 @@ fn set_data(weak: Weak<MainWindow>, files: Vec<FileInfo>, elapsed: Duration, fin
                 }
             })
             .collect();
-        if count > 1000 {
+        if files.len() > shown {
             sfiles.push(SFileInfo {
                 pre: "".into(),
                 data: [].into(),
-                name: format!("...and {} others", count - 1000).into(),
+                name: format!("...and {} others", files.len() - shown).into(),
             });
         }
         let model = VecModel::<SFileInfo>::from(sfiles);
@@ -291,13 +444,102 @@ fn set_options(weak: Weak<MainWindow>, manager: Arc<Mutex<Manager>>) {
         let ops = man.get_options();
         weak.set_case_sensitive(ops.name.case_sensitive);
         weak.set_content_case_sensitive(ops.content.case_sensitive);
+        let ftypes = match ops.name.file_types {
+            FTypes::All => "All",
+            FTypes::Files => "Files",
+            FTypes::Directories => "Directories",
+        };
+        weak.set_selected_ftypes(ftypes.into());
+        //the sort combobox has the names of the variants
+        weak.set_selected_sort(format!("{:?}", ops.sort).into());
+        set_saved_names(&weak, &ops);
+        weak.set_editor(ops.editor.into());
         weak.set_directory(ops.last_dir.into());
     });
 }
 
+fn open_result(target: OpenTarget, manager: Arc<Mutex<Manager>>, weak: Weak<MainWindow>) {
+    get_and_update_options(manager.clone(), weak.clone());
+    let editor = manager.lock().unwrap().get_options().editor;
+    let message = match target.open(&editor) {
+        Ok(()) => format!("Opened {}", target.path()),
+        Err(err) => err,
+    };
+    weak.unwrap().set_message(message.into());
+}
+
+fn set_saved_names(weak: &MainWindow, ops: &Options) {
+    let names: Vec<SharedString> = ops.saved_searches.iter().map(|x| x.name.clone().into()).collect();
+    weak.set_saved_names(ModelRc::new(VecModel::from(names)));
+}
+
+///next to the box of the pattern, terms have no box so are shown with the contents
+fn show_pattern_error(weak: &MainWindow, err: Option<&PatternError>) {
+    weak.set_name_error("".into());
+    weak.set_content_error("".into());
+    let Some(err) = err else {
+        return;
+    };
+    match err.field {
+        PatternField::Name => weak.set_name_error(err.to_string().into()),
+        PatternField::Content | PatternField::Term(_) => weak.set_content_error(err.to_string().into()),
+    }
+    weak.set_message("Invalid regex".into());
+}
+
+///errors grouped by kind. The first groups hide or show all of them, then one for each kind
+fn show_errors(weak: &MainWindow, errors: &[SearchError]) {
+    let mut groups: BTreeMap<ErrorKind, Vec<&SearchError>> = BTreeMap::new();
+    for err in errors {
+        groups.entry(err.kind()).or_default().push(err);
+    }
+    let mut labels: Vec<SharedString> = vec!["Hide errors".into(), format!("All errors ({})", errors.len()).into()];
+    labels.extend(groups.iter().map(|(kind, errs)| SharedString::from(format!("{kind} ({})", errs.len()))));
+    let shown: Vec<SharedString> = match weak.get_selected_errors() {
+        0 => vec![],
+        1 => errors.iter().map(|x| x.to_string().into()).collect(),
+        i => groups
+            .values()
+            .nth(i as usize - 2)
+            .map(|errs| errs.iter().map(|x| x.to_string().into()).collect())
+            .unwrap_or_default(),
+    };
+    weak.set_error_groups(ModelRc::new(VecModel::from(labels)));
+    weak.set_errors(ModelRc::new(VecModel::from(shown)));
+}
+
+///saves to a file picked by the user, in the format of its extension
+fn save_results(results: Vec<FileInfo>, weak: Weak<MainWindow>) {
+    let file = rfd::FileDialog::new()
+        .add_filter("JSON", &["json"])
+        .add_filter("CSV", &["csv"])
+        .add_filter("ripgrep JSON Lines", &["jsonl"])
+        .add_filter("Paths", &["txt"])
+        .set_file_name("results.json")
+        .save_file();
+    let Some(file) = file else {
+        return;
+    };
+    let results = FinalResults {
+        data: results,
+        duration: Duration::from_secs(0),
+        id: 0,
+        stopped: false,
+        truncated: false,
+    };
+    let message = match export::save(&results, &file) {
+        Ok(()) => format!("Saved {}", file.display()),
+        Err(err) => err,
+    };
+    if let Some(weak) = weak.upgrade() {
+        weak.set_message(message.into());
+    }
+}
+
 enum ExportType {
     FullPath = 1,
     Name,
+    File,
 }
 
 impl From<i32> for ExportType {
@@ -305,6 +547,7 @@ impl From<i32> for ExportType {
         match x {
             1 => ExportType::FullPath,
             2 => ExportType::Name,
+            3 => ExportType::File,
             _ => panic!("Not implemented"),
         }
     }